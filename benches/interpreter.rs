@@ -0,0 +1,39 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use netl2::logic::{ast::parse, interpeter::interpret, tokenizer::tokenize};
+
+const COUNTER_LOOP: &str = r#"
+    v x = 0
+    w x < 10000 {
+        x = x + 1
+    }
+"#;
+
+const NESTED_CALLS: &str = r#"
+    f body {
+        v y = 0
+        w y < 10 {
+            i y % 2 == 0 {
+                y = y + 1
+            }
+            y = y + 1
+        }
+    }
+    v x = 0
+    w x < 1000 {
+        body!
+        x = x + 1
+    }
+"#;
+
+fn bench_script(c: &mut Criterion, name: &str, script: &str) {
+    let ast = parse(tokenize(script).unwrap()).unwrap();
+    c.bench_function(name, |b| b.iter(|| interpret(black_box(&ast))));
+}
+
+fn interpreter_benchmarks(c: &mut Criterion) {
+    bench_script(c, "counter_loop", COUNTER_LOOP);
+    bench_script(c, "nested_calls", NESTED_CALLS);
+}
+
+criterion_group!(benches, interpreter_benchmarks);
+criterion_main!(benches);
@@ -7,5 +7,5 @@ w x < 101 {
     i x % 5 == 0 {
         p("Buzz")
     }
-    x = x + 1
-}
\ No newline at end of file
+    x += 1
+}
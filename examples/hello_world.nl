@@ -1 +1 @@
-p("Hello World!")
\ No newline at end of file
+p("Hello World!")
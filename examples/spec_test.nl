@@ -1 +1 @@
-p(2^5)
\ No newline at end of file
+p(2 ^ 5)
@@ -0,0 +1,6 @@
+fn main() {
+    if let Err(e) = netl2::lsp::run() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -1,143 +1,395 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, rc::Rc};
 
-use super::tokenizer::Token;
+use super::{
+    diagnostic::Diagnostic,
+    tokenizer::{Span, Token},
+};
 
 #[derive(Debug, Clone)]
 pub enum ASTNode {
-    Program(Box<Vec<ASTNode>>),
+    Program(Vec<ASTNode>),
     Number(i32),
     String(String),
-    Identifier(String),
-    VariableDeclaration(String, Box<ASTNode>),
-    VariableAssignment(String, Box<ASTNode>),
-    FunctionCall(String),
-    FunctionDeclaration(String, Vec<ASTNode>),
-    If(Box<ASTNode>, Vec<ASTNode>),
-    While(Box<ASTNode>, Vec<ASTNode>),
-    Print(Box<ASTNode>),
+    Identifier(String, Span),
+    VariableDeclaration(String, Option<TypeAnnotation>, Box<ASTNode>, Span),
+    VariableAssignment(String, Box<ASTNode>, Span),
+    // `x += 1`, holding the binary operator applied to the variable
+    CompoundAssignment(String, Token, Box<ASTNode>, Span),
+    FunctionCall(String, Span),
+    FunctionDeclaration(String, Vec<String>, Rc<Vec<ASTNode>>, Span),
+    // an anonymous function expression, `f(x) { r x * 2 }`
+    Lambda(Vec<String>, Rc<Vec<ASTNode>>, Span),
+    Call(Box<ASTNode>, Vec<ASTNode>, Span),
+    List(Vec<ASTNode>, Span),
+    If(Box<ASTNode>, Vec<ASTNode>, Span),
+    While(Box<ASTNode>, Vec<ASTNode>, Span),
+    Print(Box<ASTNode>, Span),
+    Return(Option<Box<ASTNode>>, Span),
+    // `t { ... } h error { ... }`, running the handler with the caught error bound to the name
+    Try(Vec<ASTNode>, String, Vec<ASTNode>, Span),
+    Raise(Box<ASTNode>, Span),
     Boolean(bool),
-    Binary(Box<ASTNode>, Token, Box<ASTNode>),
-    Unary(Token, Box<ASTNode>),
+    Nil,
+    Binary(Box<ASTNode>, Token, Box<ASTNode>, Span),
+    Unary(Token, Box<ASTNode>, Span),
+    // a statement that failed to parse, kept so tooling still sees the rest of the program
+    Error(Span),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypeAnnotation {
+    Int,
+    Str,
+    Bool,
+}
+
+// binary operators from the loosest to the tightest binding, all left associative; unary
+// operators and then ^ bind tighter than all of them
+pub const PRECEDENCE: &[&[Token]] = &[
+    &[Token::QuestionQuestion],
+    &[Token::Or],
+    &[Token::And],
+    &[
+        Token::EqualEqual,
+        Token::NotEqual,
+        Token::LessThan,
+        Token::LessThanEquals,
+        Token::GreaterThan,
+        Token::GreaterThanEquals,
+    ],
+    &[Token::BitOr],
+    &[Token::Tilde],
+    &[Token::BitAnd],
+    &[Token::ShiftLeft, Token::ShiftRight],
+    &[Token::Plus, Token::Minus],
+    &[Token::Asterisk, Token::Slash, Token::Percent],
+];
+
 struct Parser {
-    tokens: Vec<Token>,
+    tokens: Vec<(Token, Span)>,
     current: usize,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl Parser {
-    fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+    fn new(tokens: Vec<(Token, Span)>) -> Self {
+        // comments only matter to tooling like the formatter
+        let tokens = tokens
+            .into_iter()
+            .filter(|(token, _)| !matches!(token, Token::Comment(_)))
+            .collect();
+        Self {
+            tokens,
+            current: 0,
+            diagnostics: Vec::new(),
+        }
     }
 
-    fn parse(&mut self) -> Result<ASTNode, String> {
+    fn parse(&mut self) -> ASTNode {
         let mut statements = Vec::new();
 
         while self.current_token() != Token::EndOfFile {
-            let statement = self.parse_statement()?;
-            statements.push(statement);
+            statements.push(self.parse_recovering());
+        }
+
+        ASTNode::Program(statements)
+    }
+
+    // parses a statement, replacing it with an error node and skipping ahead to the next
+    // statement if it is malformed
+    fn parse_recovering(&mut self) -> ASTNode {
+        let start = self.current;
+        let span = self.current_span();
+
+        match self.parse_statement() {
+            Ok(statement) => statement,
+            Err(diagnostic) => {
+                let line = self.current_span().line;
+                self.diagnostics.push(diagnostic);
+                self.synchronize(line);
+
+                // a token that cannot start a statement would otherwise be retried forever
+                if self.current == start && self.current_token() != Token::EndOfFile {
+                    self.current += 1;
+                }
+
+                ASTNode::Error(span)
+            }
+        }
+    }
+
+    // skips to the next keyword that starts a statement, the next line, or the end of the
+    // enclosing block
+    fn synchronize(&mut self, line: usize) {
+        loop {
+            match self.current_token() {
+                Token::V
+                | Token::W
+                | Token::I
+                | Token::F
+                | Token::Print
+                | Token::Return
+                | Token::Try
+                | Token::Raise
+                | Token::RightBrace
+                | Token::EndOfFile => return,
+                _ if self.current_span().line > line => return,
+                Token::LeftBrace => self.skip_block(),
+                _ => self.current += 1,
+            }
+        }
+    }
+
+    // skips a block whose header failed to parse, so its closing brace is not mistaken for
+    // the end of the enclosing one
+    fn skip_block(&mut self) {
+        let mut depth = 0;
+        loop {
+            match self.current_token() {
+                Token::LeftBrace => depth += 1,
+                Token::RightBrace => depth -= 1,
+                Token::EndOfFile => return,
+                _ => {}
+            }
+            self.current += 1;
+            if depth == 0 {
+                return;
+            }
+        }
+    }
+
+    fn parse_block(&mut self) -> Result<Vec<ASTNode>, Diagnostic> {
+        self.consume(Token::LeftBrace)?;
+
+        let mut statements = Vec::new();
+
+        while self.current_token() != Token::RightBrace {
+            if self.current_token() == Token::EndOfFile {
+                // report the unclosed block but keep what was parsed of it
+                self.diagnostics.push(Diagnostic::error(
+                    "Expected RightBrace but got EndOfFile".to_string(),
+                    self.current_span(),
+                ));
+                return Ok(statements);
+            }
+            statements.push(self.parse_recovering());
         }
 
-        Ok(ASTNode::Program(Box::new(statements)))
+        self.consume(Token::RightBrace)?;
+
+        Ok(statements)
     }
 
     // parsing helpers
-    fn parse_statement(&mut self) -> Result<ASTNode, String> {
+    fn parse_statement(&mut self) -> Result<ASTNode, Diagnostic> {
+        // `p = 1` would otherwise fail somewhere inside the keyword's own statement; a
+        // keyword before any other token, as in `i !done {}`, starts its statement
+        if let Some(keyword) = keyword_name(&self.current_token()) {
+            if self.peek_token() == Token::Equals {
+                return Err(self.keyword_error(keyword));
+            }
+        }
+
         match self.current_token() {
             Token::V => self.parse_variable_declaration(),
             Token::W => self.parse_while(),
             Token::I => self.parse_if(),
             Token::F => self.parse_function_declaration(),
             Token::Print => self.parse_print(),
+            Token::Return => self.parse_return(),
+            Token::Try => self.parse_try(),
+            Token::Raise => self.parse_raise(),
             Token::Identifier(_) => self.parse_assignment(),
-            _ => Err(format!(
-                "Expected statement but got {:?}",
-                self.current_token()
+            _ => Err(Diagnostic::error(
+                format!("Expected statement but got {:?}", self.current_token()),
+                self.current_span(),
             )),
         }
     }
 
-    fn parse_variable_declaration(&mut self) -> Result<ASTNode, String> {
+    fn parse_variable_declaration(&mut self) -> Result<ASTNode, Diagnostic> {
         self.consume(Token::V)?;
 
+        let span = self.current_span();
         let identifier = self.consume_identifier()?;
 
+        let annotation = if self.current_token() == Token::Colon {
+            self.consume(Token::Colon)?;
+            Some(self.parse_type_annotation()?)
+        } else {
+            None
+        };
+
         self.consume(Token::Equals)?;
 
         let expression = self.parse_expression()?;
 
         Ok(ASTNode::VariableDeclaration(
             identifier,
+            annotation,
             Box::new(expression),
+            span,
         ))
     }
 
-    fn parse_while(&mut self) -> Result<ASTNode, String> {
+    fn parse_type_annotation(&mut self) -> Result<TypeAnnotation, Diagnostic> {
+        let span = self.current_span();
+        let name = self.consume_identifier()?;
+
+        match name.as_str() {
+            "int" => Ok(TypeAnnotation::Int),
+            "str" => Ok(TypeAnnotation::Str),
+            "bool" => Ok(TypeAnnotation::Bool),
+            _ => Err(Diagnostic::error(format!("Unknown type {}", name), span)),
+        }
+    }
+
+    fn parse_while(&mut self) -> Result<ASTNode, Diagnostic> {
+        let span = self.current_span();
         self.consume(Token::W)?;
 
         let condition = self.parse_expression()?;
 
-        self.consume(Token::LeftBrace)?;
+        let statements = self.parse_block()?;
 
-        let mut statements = Vec::new();
+        Ok(ASTNode::While(Box::new(condition), statements, span))
+    }
 
-        while self.current_token() != Token::RightBrace {
-            let statement = self.parse_statement()?;
-            statements.push(statement);
-        }
+    fn parse_if(&mut self) -> Result<ASTNode, Diagnostic> {
+        let span = self.current_span();
+        self.consume(Token::I)?;
 
-        self.consume(Token::RightBrace)?;
+        let condition = self.parse_expression()?;
+
+        let statements = self.parse_block()?;
 
-        Ok(ASTNode::While(Box::new(condition), statements))
+        Ok(ASTNode::If(Box::new(condition), statements, span))
     }
 
-    fn parse_if(&mut self) -> Result<ASTNode, String> {
-        self.consume(Token::I)?;
+    fn parse_function_declaration(&mut self) -> Result<ASTNode, Diagnostic> {
+        self.consume(Token::F)?;
 
-        let condition = self.parse_expression()?;
+        let span = self.current_span();
+        let identifier = self.consume_identifier()?;
 
-        self.consume(Token::LeftBrace)?;
+        let parameters = self.parse_parameters()?;
 
-        let mut statements = Vec::new();
+        let statements = self.parse_block()?;
 
-        while self.current_token() != Token::RightBrace {
-            let statement = self.parse_statement()?;
-            statements.push(statement);
+        Ok(ASTNode::FunctionDeclaration(
+            identifier,
+            parameters,
+            Rc::new(statements),
+            span,
+        ))
+    }
+
+    // the parameter list is optional, `f main {}` takes no arguments
+    fn parse_parameters(&mut self) -> Result<Vec<String>, Diagnostic> {
+        let mut parameters = Vec::new();
+        if self.current_token() != Token::LeftParen {
+            return Ok(parameters);
         }
 
-        self.consume(Token::RightBrace)?;
+        self.consume(Token::LeftParen)?;
+        while self.current_token() != Token::RightParen {
+            let span = self.current_span();
+            let parameter = self.consume_identifier()?;
+            if parameters.contains(&parameter) {
+                return Err(Diagnostic::error(
+                    format!("Parameter {} is declared twice", parameter),
+                    span,
+                ));
+            }
+            parameters.push(parameter);
 
-        Ok(ASTNode::If(Box::new(condition), statements))
+            if self.current_token() != Token::RightParen {
+                self.consume(Token::Comma)?;
+            }
+        }
+        self.consume(Token::RightParen)?;
+
+        Ok(parameters)
     }
 
-    fn parse_function_declaration(&mut self) -> Result<ASTNode, String> {
-        self.consume(Token::F)?;
+    // comma separated expressions up to the closing token, allowing a trailing comma
+    fn parse_arguments(&mut self, close: Token) -> Result<Vec<ASTNode>, Diagnostic> {
+        let mut arguments = Vec::new();
+        while self.current_token() != close {
+            arguments.push(self.parse_expression()?);
 
-        let identifier = self.consume_identifier()?;
+            if self.current_token() != close {
+                self.consume(Token::Comma)?;
+            }
+        }
+        self.consume(close)?;
 
-        self.consume(Token::LeftBrace)?;
+        Ok(arguments)
+    }
 
-        let mut statements = Vec::new();
+    fn parse_calls(&mut self, mut callee: ASTNode) -> Result<ASTNode, Diagnostic> {
+        while self.current_token() == Token::LeftParen {
+            let span = self.current_span();
+            self.consume(Token::LeftParen)?;
 
-        while self.current_token() != Token::RightBrace {
-            let statement = self.parse_statement()?;
-            statements.push(statement);
+            let arguments = self.parse_arguments(Token::RightParen)?;
+
+            callee = ASTNode::Call(Box::new(callee), arguments, span);
         }
+        Ok(callee)
+    }
 
-        self.consume(Token::RightBrace)?;
+    fn parse_print(&mut self) -> Result<ASTNode, Diagnostic> {
+        let span = self.current_span();
+        self.consume(Token::Print)?;
 
-        Ok(ASTNode::FunctionDeclaration(identifier, statements))
+        let expression = self.parse_expression()?;
+
+        Ok(ASTNode::Print(Box::new(expression), span))
     }
 
-    fn parse_print(&mut self) -> Result<ASTNode, String> {
-        self.consume(Token::Print)?;
+    fn parse_return(&mut self) -> Result<ASTNode, Diagnostic> {
+        let span = self.current_span();
+        self.consume(Token::Return)?;
+
+        // without terminators, a returned value has to start on the same line as the r
+        let expression = if self.current_span().line == span.line
+            && self.current_token() != Token::RightBrace
+            && self.current_token() != Token::EndOfFile
+        {
+            Some(Box::new(self.parse_expression()?))
+        } else {
+            None
+        };
+
+        Ok(ASTNode::Return(expression, span))
+    }
+
+    fn parse_try(&mut self) -> Result<ASTNode, Diagnostic> {
+        let span = self.current_span();
+        self.consume(Token::Try)?;
+
+        let statements = self.parse_block()?;
+
+        self.consume(Token::Catch)?;
+        let name = self.consume_identifier()?;
+
+        let handler = self.parse_block()?;
+
+        Ok(ASTNode::Try(statements, name, handler, span))
+    }
+
+    fn parse_raise(&mut self) -> Result<ASTNode, Diagnostic> {
+        let span = self.current_span();
+        self.consume(Token::Raise)?;
 
         let expression = self.parse_expression()?;
 
-        Ok(ASTNode::Print(Box::new(expression)))
+        Ok(ASTNode::Raise(Box::new(expression), span))
     }
 
-    fn parse_assignment(&mut self) -> Result<ASTNode, String> {
+    fn parse_assignment(&mut self) -> Result<ASTNode, Diagnostic> {
+        let span = self.current_span();
         let identifier = self.consume_identifier()?;
 
         if self.current_token() == Token::Equals {
@@ -148,74 +400,110 @@ impl Parser {
             Ok(ASTNode::VariableAssignment(
                 identifier,
                 Box::new(expression),
+                span,
+            ))
+        } else if let Some(operator) = compound_operator(&self.current_token()) {
+            self.next_token()?;
+
+            let expression = self.parse_expression()?;
+
+            Ok(ASTNode::CompoundAssignment(
+                identifier,
+                operator,
+                Box::new(expression),
+                span,
             ))
         } else if self.current_token() == Token::Bang {
             self.consume(Token::Bang)?;
 
-            Ok(ASTNode::FunctionCall(identifier))
+            Ok(ASTNode::FunctionCall(identifier, span))
+        } else if self.current_token() == Token::LeftParen {
+            self.parse_calls(ASTNode::Identifier(identifier, span))
         } else {
-            Err(format!(
-                "Expected equals or bang but got {:?}",
-                self.current_token()
+            Err(Diagnostic::error(
+                format!(
+                    "Expected equals, an assignment operator, bang or left paren but got {:?}",
+                    self.current_token()
+                ),
+                self.current_span(),
             ))
         }
     }
 
-    fn parse_expression(&mut self) -> Result<ASTNode, String> {
-        let mut left_node = self.parse_simple_expression()?;
-        while self.current_token() == Token::And || self.current_token() == Token::Or {
-            let operator = self.current_token();
-            self.consume(operator.clone())?;
+    fn parse_expression(&mut self) -> Result<ASTNode, Diagnostic> {
+        let expression = self.parse_binary(0)?;
 
-            let right_node = self.parse_simple_expression()?;
-
-            left_node = ASTNode::Binary(Box::new(left_node), operator, Box::new(right_node));
+        // assignment is a statement, so an = after an expression is almost always a typo
+        if self.current_token() == Token::Equals {
+            return Err(Diagnostic::error(
+                "Unexpected = in an expression, did you mean ==?".to_string(),
+                self.current_span(),
+            ));
         }
-        Ok(left_node)
+
+        Ok(expression)
     }
 
-    fn parse_simple_expression(&mut self) -> Result<ASTNode, String> {
-        let mut left_node = self.parse_term()?;
-        while self.current_token() == Token::Plus
-            || self.current_token() == Token::Minus
-            || self.current_token() == Token::Asterisk
-            || self.current_token() == Token::Slash
-            || self.current_token() == Token::Percent
-            || self.current_token() == Token::Pow
-            || self.current_token() == Token::LessThan
-            || self.current_token() == Token::GreaterThan
-            || self.current_token() == Token::LessThanEquals
-            || self.current_token() == Token::GreaterThanEquals
-            || self.current_token() == Token::EqualEqual
-            || self.current_token() == Token::NotEqual
-            || self.current_token() == Token::Equals
-        {
+    // parses the operators of one precedence level, deferring operands to the next one
+    fn parse_binary(&mut self, level: usize) -> Result<ASTNode, Diagnostic> {
+        let operators = match PRECEDENCE.get(level) {
+            Some(operators) => operators,
+            None => return self.parse_unary(),
+        };
+
+        let mut left_node = self.parse_binary(level + 1)?;
+        while operators.contains(&self.current_token()) {
             let operator = self.current_token();
+            let span = self.current_span();
             self.consume(operator.clone())?;
 
-            let right_node = self.parse_term()?;
+            let right_node = self.parse_binary(level + 1)?;
 
-            left_node = ASTNode::Binary(Box::new(left_node), operator, Box::new(right_node));
+            left_node = ASTNode::Binary(Box::new(left_node), operator, Box::new(right_node), span);
         }
         Ok(left_node)
     }
 
-    fn parse_term(&mut self) -> Result<ASTNode, String> {
-        let mut left_node = self.parse_factor()?;
+    // unary operators bind tighter than every binary operator except ^, so -a + b is
+    // (-a) + b and -a ^ 2 is -(a ^ 2)
+    fn parse_unary(&mut self) -> Result<ASTNode, Diagnostic> {
+        match self.current_token() {
+            Token::Minus | Token::Bang | Token::Tilde => {
+                let operator = self.current_token();
+                let span = self.current_span();
+                self.consume(operator.clone())?;
 
-        while self.current_token() == Token::Equals || self.current_token() == Token::NotEqual {
-            let operator = self.current_token();
-            self.consume(operator.clone())?;
+                let expression = self.parse_unary()?;
 
-            let right_node = self.parse_factor()?;
+                Ok(ASTNode::Unary(operator, Box::new(expression), span))
+            }
+            _ => self.parse_power(),
+        }
+    }
+
+    // ^ is right associative and its exponent may be negated, as in 2 ^ -1
+    fn parse_power(&mut self) -> Result<ASTNode, Diagnostic> {
+        let factor = self.parse_factor()?;
+        let base = self.parse_calls(factor)?;
 
-            left_node = ASTNode::Binary(Box::new(left_node), operator, Box::new(right_node));
+        if self.current_token() != Token::Pow {
+            return Ok(base);
         }
 
-        Ok(left_node)
+        let span = self.current_span();
+        self.consume(Token::Pow)?;
+
+        let exponent = self.parse_unary()?;
+
+        Ok(ASTNode::Binary(
+            Box::new(base),
+            Token::Pow,
+            Box::new(exponent),
+            span,
+        ))
     }
 
-    fn parse_factor(&mut self) -> Result<ASTNode, String> {
+    fn parse_factor(&mut self) -> Result<ASTNode, Diagnostic> {
         match self.current_token() {
             Token::Number(value) => {
                 self.next_token()?;
@@ -229,9 +517,14 @@ impl Parser {
                 self.next_token()?;
                 Ok(ASTNode::Boolean(value))
             }
+            Token::Nil => {
+                self.next_token()?;
+                Ok(ASTNode::Nil)
+            }
             Token::Identifier(value) => {
+                let span = self.current_span();
                 self.next_token()?;
-                Ok(ASTNode::Identifier(value))
+                Ok(ASTNode::Identifier(value, span))
             }
             Token::LeftParen => {
                 self.consume(Token::LeftParen)?;
@@ -242,23 +535,30 @@ impl Parser {
 
                 Ok(expression)
             }
-            Token::Minus => {
-                self.consume(Token::Minus)?;
+            Token::LeftBracket => {
+                let span = self.current_span();
+                self.consume(Token::LeftBracket)?;
 
-                let expression = self.parse_expression()?;
+                let elements = self.parse_arguments(Token::RightBracket)?;
 
-                Ok(ASTNode::Unary(Token::Minus, Box::new(expression)))
+                Ok(ASTNode::List(elements, span))
             }
-            Token::Bang => {
-                self.consume(Token::Bang)?;
+            Token::F => {
+                let span = self.current_span();
+                self.consume(Token::F)?;
 
-                let expression = self.parse_expression()?;
+                let parameters = self.parse_parameters()?;
+
+                let statements = self.parse_block()?;
 
-                Ok(ASTNode::Unary(Token::Bang, Box::new(expression)))
+                Ok(ASTNode::Lambda(parameters, Rc::new(statements), span))
             }
-            _ => Err(format!(
-                "Expected number, string, boolean, identifier, or left paren but got {:?}",
-                self.current_token()
+            _ => Err(Diagnostic::error(
+                format!(
+                    "Expected number, string, boolean, nil, identifier, list, function, or left paren but got {:?}",
+                    self.current_token()
+                ),
+                self.current_span(),
             )),
         }
     }
@@ -266,45 +566,96 @@ impl Parser {
     // token helpers
 
     fn current_token(&self) -> Token {
-        self.tokens[self.current].clone()
+        self.tokens[self.current].0.clone()
+    }
+
+    fn current_span(&self) -> Span {
+        self.tokens[self.current].1
     }
 
-    fn consume(&mut self, token: Token) -> Result<Token, String> {
+    // skips the misused keyword so recovery does not start a statement with it
+    fn keyword_error(&mut self, keyword: &str) -> Diagnostic {
+        let span = self.current_span();
+        self.current += 1;
+        Diagnostic::error(
+            format!("{} is a keyword and cannot be used as a name", keyword),
+            span,
+        )
+    }
+
+    fn peek_token(&self) -> Token {
+        match self.tokens.get(self.current + 1) {
+            Some((token, _)) => token.clone(),
+            None => Token::EndOfFile,
+        }
+    }
+
+    fn consume(&mut self, token: Token) -> Result<Token, Diagnostic> {
         if self.current_token() == token {
             self.current += 1;
             Ok(token)
         } else {
-            Err(format!(
-                "Expected {:?} but got {:?}",
-                token,
-                self.current_token()
+            Err(Diagnostic::error(
+                format!("Expected {:?} but got {:?}", token, self.current_token()),
+                self.current_span(),
             ))
         }
     }
 
-    fn consume_identifier(&mut self) -> Result<String, String> {
+    fn consume_identifier(&mut self) -> Result<String, Diagnostic> {
         if let Token::Identifier(identifier) = self.current_token() {
             self.next_token()?;
             Ok(identifier)
+        } else if let Some(keyword) = keyword_name(&self.current_token()) {
+            Err(self.keyword_error(keyword))
         } else {
-            Err(format!(
-                "Expected identifier but got {:?}",
-                self.current_token()
+            Err(Diagnostic::error(
+                format!("Expected identifier but got {:?}", self.current_token()),
+                self.current_span(),
             ))
         }
     }
 
-    fn next_token(&mut self) -> Result<(), String> {
+    fn next_token(&mut self) -> Result<(), Diagnostic> {
         if self.current < self.tokens.len() - 1 {
             self.current += 1;
             Ok(())
         } else {
-            Err("No more tokens".to_string())
+            Err(Diagnostic::error(
+                "No more tokens".to_string(),
+                self.current_span(),
+            ))
         }
     }
 }
 
-impl Eq for ASTNode {}
+// the binary operator behind a compound assignment token like +=
+pub fn compound_operator(token: &Token) -> Option<Token> {
+    match token {
+        Token::PlusEquals => Some(Token::Plus),
+        Token::MinusEquals => Some(Token::Minus),
+        Token::AsteriskEquals => Some(Token::Asterisk),
+        Token::SlashEquals => Some(Token::Slash),
+        Token::PercentEquals => Some(Token::Percent),
+        Token::PowEquals => Some(Token::Pow),
+        _ => None,
+    }
+}
+
+fn keyword_name(token: &Token) -> Option<&'static str> {
+    match token {
+        Token::V => Some("v"),
+        Token::W => Some("w"),
+        Token::I => Some("i"),
+        Token::F => Some("f"),
+        Token::Print => Some("p"),
+        Token::Return => Some("r"),
+        Token::Try => Some("t"),
+        Token::Catch => Some("h"),
+        Token::Raise => Some("e"),
+        _ => None,
+    }
+}
 
 impl PartialOrd for ASTNode {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
@@ -316,24 +667,30 @@ impl PartialOrd for ASTNode {
     }
 }
 
-impl Ord for ASTNode {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).unwrap()
-    }
-}
-
 impl PartialEq for ASTNode {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (ASTNode::Number(a), ASTNode::Number(b)) => a == b,
             (ASTNode::String(a), ASTNode::String(b)) => a == b,
-            (ASTNode::Identifier(a), ASTNode::Identifier(b)) => a == b,
+            (ASTNode::Identifier(a, _), ASTNode::Identifier(b, _)) => a == b,
             _ => false,
         }
     }
 }
 
-pub fn parse(tokens: Vec<Token>) -> Result<ASTNode, String> {
+// parses a whole program, failing with every error found if any statement is malformed
+pub fn parse(tokens: Vec<(Token, Span)>) -> Result<ASTNode, Vec<Diagnostic>> {
+    let (ast, diagnostics) = parse_partial(tokens);
+    if diagnostics.is_empty() {
+        Ok(ast)
+    } else {
+        Err(diagnostics)
+    }
+}
+
+// parses as much of a program as possible, with error nodes in place of malformed statements
+pub fn parse_partial(tokens: Vec<(Token, Span)>) -> (ASTNode, Vec<Diagnostic>) {
     let mut parser = Parser::new(tokens);
-    parser.parse()
+    let ast = parser.parse();
+    (ast, parser.diagnostics)
 }
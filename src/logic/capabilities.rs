@@ -0,0 +1,159 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+// what a script may do outside of the interpreter, each guarding a group of built-ins
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    Stdout,
+    Stdin,
+    FsRead,
+    FsWrite,
+    Env,
+    Clock,
+    Random,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 7] = [
+        Capability::Stdout,
+        Capability::Stdin,
+        Capability::FsRead,
+        Capability::FsWrite,
+        Capability::Env,
+        Capability::Clock,
+        Capability::Random,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Stdout => "stdout",
+            Capability::Stdin => "stdin",
+            Capability::FsRead => "fs-read",
+            Capability::FsWrite => "fs-write",
+            Capability::Env => "env",
+            Capability::Clock => "clock",
+            Capability::Random => "random",
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// The capabilities granted to one interpreter. File system access can be granted for
+// everything or only for some directories. By default scripts can print, read input and
+// use the clock and random numbers, but not touch files or the environment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    granted: HashSet<Capability>,
+    // directories files may be read from or written to, empty for everywhere
+    read_paths: Vec<PathBuf>,
+    write_paths: Vec<PathBuf>,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::none()
+            .allow(Capability::Stdout)
+            .allow(Capability::Stdin)
+            .allow(Capability::Clock)
+            .allow(Capability::Random)
+    }
+}
+
+impl Capabilities {
+    pub fn none() -> Self {
+        Self {
+            granted: HashSet::new(),
+            read_paths: Vec::new(),
+            write_paths: Vec::new(),
+        }
+    }
+
+    pub fn all() -> Self {
+        Capability::ALL
+            .into_iter()
+            .fold(Self::none(), |capabilities, capability| {
+                capabilities.allow(capability)
+            })
+    }
+
+    pub fn allow(mut self, capability: Capability) -> Self {
+        self.granted.insert(capability);
+        self.set_paths(capability, Vec::new());
+        self
+    }
+
+    // grants file system access below the given directories only
+    pub fn allow_paths(mut self, capability: Capability, paths: Vec<PathBuf>) -> Self {
+        self.granted.insert(capability);
+        self.set_paths(capability, paths);
+        self
+    }
+
+    pub fn deny(mut self, capability: Capability) -> Self {
+        self.granted.remove(&capability);
+        self.set_paths(capability, Vec::new());
+        self
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.granted.contains(&capability)
+    }
+
+    pub fn allows_path(&self, capability: Capability, path: &Path) -> bool {
+        if !self.allows(capability) {
+            return false;
+        }
+
+        let paths = self.paths(capability);
+        if paths.is_empty() {
+            return true;
+        }
+
+        // paths are compared after resolving links and .., so ../ cannot escape a directory
+        match resolve(path) {
+            Some(path) => paths
+                .iter()
+                .filter_map(|allowed| resolve(allowed))
+                .any(|allowed| path.starts_with(allowed)),
+            None => false,
+        }
+    }
+
+    fn paths(&self, capability: Capability) -> &[PathBuf] {
+        match capability {
+            Capability::FsRead => &self.read_paths,
+            Capability::FsWrite => &self.write_paths,
+            _ => &[],
+        }
+    }
+
+    fn set_paths(&mut self, capability: Capability, paths: Vec<PathBuf>) {
+        match capability {
+            Capability::FsRead => self.read_paths = paths,
+            Capability::FsWrite => self.write_paths = paths,
+            _ => {}
+        }
+    }
+}
+
+// absolute path without links, for files that do not exist yet through their directory
+fn resolve(path: &Path) -> Option<PathBuf> {
+    if let Ok(path) = path.canonicalize() {
+        return Some(path);
+    }
+
+    let name = path.file_name()?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    Some(parent.canonicalize().ok()?.join(name))
+}
@@ -0,0 +1,64 @@
+use std::fmt;
+
+use super::tokenizer::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    // stable identifier of the rule that produced the diagnostic, if any
+    pub code: Option<&'static str>,
+}
+
+impl Diagnostic {
+    pub fn error(message: String, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            span,
+            code: None,
+        }
+    }
+
+    pub fn warning(message: String, span: Span) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+            span,
+            code: None,
+        }
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+        };
+        match self.code {
+            Some(code) => write!(
+                f,
+                "{}[{}]: {} at {}",
+                severity, code, self.message, self.span
+            ),
+            None => write!(f, "{}: {} at {}", severity, self.message, self.span),
+        }
+    }
+}
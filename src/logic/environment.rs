@@ -0,0 +1,50 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use super::value::Value;
+
+// Variables live in environments chained from the innermost function call out to the
+// file. Functions keep the environment they were declared in, so they see the variables
+// around their declaration even after the declaring call has returned.
+#[derive(Default, Clone)]
+pub struct Environment {
+    variables: HashMap<String, Value>,
+    parent: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_parent(parent: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            variables: HashMap::new(),
+            parent: Some(parent),
+        }
+    }
+
+    pub fn declare(&mut self, name: &str, value: Value) {
+        self.variables.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match self.variables.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.parent.as_ref()?.borrow().get(name),
+        }
+    }
+
+    // changes the innermost variable of that name, returning false if there is none
+    pub fn assign(&mut self, name: &str, value: Value) -> bool {
+        match self.variables.get_mut(name) {
+            Some(variable) => {
+                *variable = value;
+                true
+            }
+            None => match &self.parent {
+                Some(parent) => parent.borrow_mut().assign(name, value),
+                None => false,
+            },
+        }
+    }
+}
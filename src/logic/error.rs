@@ -0,0 +1,197 @@
+use std::{fmt, time::Duration};
+
+use super::{
+    capabilities::Capability,
+    tokenizer::{suggestion, Span},
+    value::ErrorValue,
+};
+
+// number of calls printed at each end of a long call chain
+const SHOWN_FRAMES: usize = 5;
+
+// a function call that was still running when an error happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub function: String,
+    // where the function was called
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeError {
+    UndeclaredVariable(String, Span),
+    // operator and the types of its operands
+    InvalidOperands(String, Vec<&'static str>, Span),
+    DivisionByZero(Span),
+    Overflow(Span),
+    NotCallable(&'static str, Span),
+    // function name, expected and actual number of arguments
+    WrongArgumentCount(String, usize, usize, Span),
+    // an operator or built-in function got a value it cannot handle
+    InvalidValue(String, Span),
+    // the calls that were running, outermost first
+    StackOverflow(Vec<Frame>),
+    // the limit that was exceeded
+    StepLimit(u64, Span),
+    Timeout(Duration, Span),
+    MemoryLimit(usize, Span),
+    // built-in function or path that needs a capability the interpreter was not granted
+    PermissionDenied(String, Capability, Span),
+    // source text of the checked expression, filled in by the interpreter, and for
+    // assert_eq the values that differed
+    AssertionFailed(Option<String>, Option<(String, String)>, Span),
+    // raised by the script with e
+    Raised(ErrorValue, Span),
+    // not a failure, exit unwinds the script through the same path as errors
+    Exit(i32, Span),
+}
+
+impl RuntimeError {
+    pub fn message(&self) -> String {
+        match self {
+            RuntimeError::UndeclaredVariable(name, _) => {
+                format!("Variable {} is not declared", name)
+            }
+            RuntimeError::InvalidOperands(operator, types, _) => {
+                let message = format!("Cannot apply {} to {}", operator, types.join(" and "));
+                match (operator.as_str(), types.as_slice()) {
+                    ("&" | "|", ["bool", "bool"]) => {
+                        let hint = operator.chars().next().and_then(suggestion);
+                        format!("{} ({})", message, hint.unwrap_or_default())
+                    }
+                    _ => message,
+                }
+            }
+            RuntimeError::DivisionByZero(_) => "Division by zero".to_string(),
+            RuntimeError::Overflow(_) => "Integer overflow".to_string(),
+            RuntimeError::NotCallable(kind, _) => {
+                format!("A value of type {} cannot be called", kind)
+            }
+            RuntimeError::WrongArgumentCount(name, expected, actual, _) => format!(
+                "Function {} expects {} arguments but got {}",
+                name, expected, actual
+            ),
+            RuntimeError::InvalidValue(message, _) => message.clone(),
+            RuntimeError::StackOverflow(frames) => format!(
+                "Stack overflow, more than {} nested function calls",
+                frames.len().saturating_sub(1)
+            ),
+            RuntimeError::StepLimit(steps, _) => format!("Step limit of {} exceeded", steps),
+            RuntimeError::Timeout(timeout, _) => {
+                format!("Time limit of {}ms exceeded", timeout.as_millis())
+            }
+            RuntimeError::MemoryLimit(bytes, _) => {
+                format!("Memory limit of {} bytes exceeded", bytes)
+            }
+            RuntimeError::PermissionDenied(subject, capability, _) => format!(
+                "Permission denied, {} needs the {} capability",
+                subject, capability
+            ),
+            RuntimeError::AssertionFailed(Some(expression), _, _) => {
+                format!("Assertion failed: {}", expression)
+            }
+            RuntimeError::AssertionFailed(None, _, _) => "Assertion failed".to_string(),
+            RuntimeError::Raised(error, _) => error.message.clone(),
+            RuntimeError::Exit(code, _) => format!("Script exited with code {}", code),
+        }
+    }
+
+    // the kind of the error value a script catches this error as
+    pub fn kind(&self) -> &str {
+        match self {
+            RuntimeError::UndeclaredVariable(..) => "undeclared_variable",
+            RuntimeError::InvalidOperands(..) => "invalid_operands",
+            RuntimeError::DivisionByZero(_) => "division_by_zero",
+            RuntimeError::Overflow(_) => "overflow",
+            RuntimeError::NotCallable(..) => "not_callable",
+            RuntimeError::WrongArgumentCount(..) => "wrong_argument_count",
+            RuntimeError::InvalidValue(..) => "invalid_value",
+            RuntimeError::StackOverflow(_) => "stack_overflow",
+            RuntimeError::StepLimit(..) => "step_limit",
+            RuntimeError::Timeout(..) => "timeout",
+            RuntimeError::MemoryLimit(..) => "memory_limit",
+            RuntimeError::PermissionDenied(..) => "permission_denied",
+            RuntimeError::AssertionFailed(..) => "assertion_failed",
+            RuntimeError::Raised(error, _) => &error.kind,
+            RuntimeError::Exit(..) => "exit",
+        }
+    }
+
+    // exiting and exceeding a limit end the script even inside t, so a script can neither
+    // keep itself from exiting nor run past its limits
+    pub fn is_catchable(&self) -> bool {
+        !matches!(
+            self,
+            RuntimeError::StepLimit(..)
+                | RuntimeError::Timeout(..)
+                | RuntimeError::MemoryLimit(..)
+                | RuntimeError::Exit(..)
+        )
+    }
+
+    // status the process should exit with
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            RuntimeError::Exit(code, _) => *code,
+            _ => 1,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            RuntimeError::UndeclaredVariable(_, span)
+            | RuntimeError::InvalidOperands(_, _, span)
+            | RuntimeError::DivisionByZero(span)
+            | RuntimeError::Overflow(span)
+            | RuntimeError::NotCallable(_, span)
+            | RuntimeError::WrongArgumentCount(_, _, _, span)
+            | RuntimeError::InvalidValue(_, span)
+            | RuntimeError::StepLimit(_, span)
+            | RuntimeError::Timeout(_, span)
+            | RuntimeError::MemoryLimit(_, span)
+            | RuntimeError::PermissionDenied(_, _, span)
+            | RuntimeError::AssertionFailed(_, _, span)
+            | RuntimeError::Raised(_, span)
+            | RuntimeError::Exit(_, span) => *span,
+            RuntimeError::StackOverflow(frames) => {
+                frames.last().map(|frame| frame.span).unwrap_or_default()
+            }
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error: {} at {}", self.message(), self.span())?;
+
+        if let RuntimeError::AssertionFailed(_, Some((left, right)), _) = self {
+            write!(f, "\n  left: {}\n  right: {}", left, right)?;
+        }
+
+        // innermost call first, with runs of the same recursive call folded into one line
+        if let RuntimeError::StackOverflow(frames) = self {
+            let mut runs: Vec<(&Frame, usize)> = Vec::new();
+            for frame in frames.iter().rev() {
+                match runs.last_mut() {
+                    Some((last, count)) if *last == frame => *count += 1,
+                    _ => runs.push((frame, 1)),
+                }
+            }
+
+            for (index, (frame, count)) in runs.iter().enumerate() {
+                if index == SHOWN_FRAMES && runs.len() > SHOWN_FRAMES * 2 {
+                    write!(f, "\n  ... {} more", runs.len() - SHOWN_FRAMES * 2)?;
+                }
+                if index >= SHOWN_FRAMES && index < runs.len().saturating_sub(SHOWN_FRAMES) {
+                    continue;
+                }
+
+                write!(f, "\n  in {} called at {}", frame.function, frame.span)?;
+                if *count > 1 {
+                    write!(f, " ({} times)", count)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
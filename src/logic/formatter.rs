@@ -0,0 +1,428 @@
+use super::{
+    ast::{parse, ASTNode, TypeAnnotation, PRECEDENCE},
+    diagnostic::Diagnostic,
+    tokenizer::{tokenize, Span, Token},
+};
+
+const INDENT: &str = "    ";
+
+struct Formatter {
+    // source text of every number literal, in order
+    numbers: Vec<String>,
+    next_number: usize,
+    comments: Vec<(String, Span)>,
+    next_comment: usize,
+    // closing brace of every block, in the order the blocks are opened
+    block_ends: Vec<Span>,
+    next_block: usize,
+    output: String,
+    indent: usize,
+    last_line: usize,
+}
+
+impl Formatter {
+    fn new(code: &str, tokens: &[(Token, Span)]) -> Self {
+        let lines: Vec<&str> = code.lines().collect();
+        let mut numbers = Vec::new();
+        let mut comments = Vec::new();
+        let mut block_ends = Vec::new();
+        let mut open_blocks = Vec::new();
+
+        for (token, span) in tokens {
+            match token {
+                Token::Comment(comment) => comments.push((comment.clone(), *span)),
+                Token::Number(_) => numbers.push(
+                    lines[span.line - 1]
+                        .chars()
+                        .skip(span.column - 1)
+                        .take_while(|ch| ch.is_ascii_alphanumeric())
+                        .collect(),
+                ),
+                Token::LeftBrace => {
+                    open_blocks.push(block_ends.len());
+                    block_ends.push(*span);
+                }
+                Token::RightBrace => {
+                    if let Some(block) = open_blocks.pop() {
+                        block_ends[block] = *span;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            numbers,
+            next_number: 0,
+            comments,
+            next_comment: 0,
+            block_ends,
+            next_block: 0,
+            output: String::new(),
+            indent: 0,
+            last_line: 0,
+        }
+    }
+
+    fn format(mut self, ast: &ASTNode) -> String {
+        match ast {
+            ASTNode::Program(statements) => self.format_statements(statements),
+            statement => self.format_statement(statement),
+        }
+        self.format_comments_before(usize::MAX);
+
+        self.output
+    }
+
+    // statements
+
+    fn format_statements(&mut self, statements: &[ASTNode]) {
+        for statement in statements {
+            self.format_statement(statement);
+        }
+    }
+
+    fn format_statement(&mut self, ast: &ASTNode) {
+        let span = statement_span(ast);
+        self.format_comments_before(span.line);
+        self.separate(span.line);
+
+        match ast {
+            ASTNode::Program(statements) => self.format_statements(statements),
+            ASTNode::VariableDeclaration(name, annotation, value, _) => {
+                let annotation = match annotation {
+                    Some(annotation) => format!(": {}", annotation_name(annotation)),
+                    None => String::new(),
+                };
+                let value = self.format_expression(value);
+                self.write_line(format!("v {}{} = {}", name, annotation, value), span);
+            }
+            ASTNode::VariableAssignment(name, value, _) => {
+                let value = self.format_expression(value);
+                self.write_line(format!("{} = {}", name, value), span);
+            }
+            ASTNode::CompoundAssignment(name, operator, value, _) => {
+                let value = self.format_expression(value);
+                let operator = operator_symbol(operator);
+                self.write_line(format!("{} {}= {}", name, operator, value), span);
+            }
+            ASTNode::FunctionCall(name, _) => self.write_line(format!("{}!", name), span),
+            ASTNode::Print(expression, _) => {
+                let expression = self.format_expression(expression);
+                self.write_line(format!("p({})", expression), span);
+            }
+            ASTNode::Return(expression, _) => {
+                let line = match expression {
+                    Some(expression) => format!("r {}", self.format_expression(expression)),
+                    None => "r".to_string(),
+                };
+                self.write_line(line, span);
+            }
+            ASTNode::FunctionDeclaration(name, parameters, body, _) => {
+                let header = if parameters.is_empty() {
+                    format!("f {}", name)
+                } else {
+                    format!("f {}({})", name, parameters.join(", "))
+                };
+                self.format_block(header, body, span);
+            }
+            ASTNode::If(condition, body, _) => {
+                let condition = self.format_expression(condition);
+                self.format_block(format!("i {}", condition), body, span);
+            }
+            ASTNode::While(condition, body, _) => {
+                let condition = self.format_expression(condition);
+                self.format_block(format!("w {}", condition), body, span);
+            }
+            // the handler starts on the line that closes the body, `} h error {`
+            ASTNode::Try(body, name, handler, _) => {
+                let end = self.block_end(span);
+
+                self.write_line("t {".to_string(), span);
+                self.indent += 1;
+                self.format_statements(body);
+                self.format_comments_before(end.line);
+                self.indent -= 1;
+
+                self.format_block(format!("}} h {}", name), handler, end);
+            }
+            ASTNode::Raise(expression, _) => {
+                let expression = self.format_expression(expression);
+                self.write_line(format!("e {}", expression), span);
+            }
+            expression => {
+                let line = self.format_expression(expression);
+                self.write_line(line, span);
+            }
+        }
+    }
+
+    fn format_block(&mut self, header: String, body: &[ASTNode], span: Span) {
+        let end = self.block_end(span);
+
+        if body.is_empty() && !self.has_comment_before(end.line) {
+            self.write_line(format!("{} {{}}", header), end);
+            return;
+        }
+
+        self.write_line(format!("{} {{", header), span);
+
+        self.indent += 1;
+        self.format_statements(body);
+        self.format_comments_before(end.line);
+        self.indent -= 1;
+
+        self.write_line("}".to_string(), end);
+    }
+
+    // lambdas are formatted like blocks, with the body indented one level deeper than the
+    // statement they are part of
+    fn format_lambda(&mut self, parameters: &[String], body: &[ASTNode], span: Span) -> String {
+        let header = format!("f({})", parameters.join(", "));
+        let end = self.block_end(span);
+
+        if body.is_empty() && !self.has_comment_before(end.line) {
+            return format!("{} {{}}", header);
+        }
+
+        let outer = std::mem::take(&mut self.output);
+        self.indent += 1;
+        self.format_statements(body);
+        self.format_comments_before(end.line);
+        self.indent -= 1;
+        let body = std::mem::replace(&mut self.output, outer);
+        self.last_line = self.last_line.max(end.line);
+
+        format!("{} {{\n{}{}}}", header, body, INDENT.repeat(self.indent))
+    }
+
+    fn block_end(&mut self, span: Span) -> Span {
+        let end = self
+            .block_ends
+            .get(self.next_block)
+            .copied()
+            .unwrap_or(span);
+        self.next_block += 1;
+        end
+    }
+
+    // comments and layout
+
+    fn has_comment_before(&self, line: usize) -> bool {
+        self.comments
+            .get(self.next_comment)
+            .is_some_and(|(_, span)| span.line < line)
+    }
+
+    fn format_comments_before(&mut self, line: usize) {
+        while let Some((comment, span)) = self.comments.get(self.next_comment).cloned() {
+            if span.line >= line {
+                break;
+            }
+            self.next_comment += 1;
+
+            self.separate(span.line);
+            self.write_indent();
+            self.output.push_str(&format!("//{}\n", comment));
+            self.last_line = span.line;
+        }
+    }
+
+    // keeps at most one blank line wherever the source had one or more
+    fn separate(&mut self, line: usize) {
+        if self.last_line != 0
+            && line > self.last_line + 1
+            && !self.output.is_empty()
+            && !self.output.ends_with("{\n")
+        {
+            self.output.push('\n');
+        }
+    }
+
+    fn write_line(&mut self, line: String, span: Span) {
+        self.write_indent();
+        self.output.push_str(&line);
+
+        // a comment on the same line as the code stays there
+        if let Some((comment, comment_span)) = self.comments.get(self.next_comment) {
+            if comment_span.line == span.line {
+                self.output.push_str(&format!(" //{}", comment));
+                self.next_comment += 1;
+            }
+        }
+
+        self.output.push('\n');
+        self.last_line = self.last_line.max(span.line);
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.indent {
+            self.output.push_str(INDENT);
+        }
+    }
+
+    // expressions
+
+    fn format_list(&mut self, expressions: &[ASTNode]) -> String {
+        let expressions: Vec<String> = expressions
+            .iter()
+            .map(|expression| self.format_expression(expression))
+            .collect();
+        expressions.join(", ")
+    }
+
+    fn format_expression(&mut self, ast: &ASTNode) -> String {
+        match ast {
+            // numbers keep the spelling of their literal, like 0xFF
+            ASTNode::Number(value) => {
+                self.next_number += 1;
+                self.numbers
+                    .get(self.next_number - 1)
+                    .cloned()
+                    .unwrap_or_else(|| value.to_string())
+            }
+            ASTNode::String(value) => format!("\"{}\"", value),
+            ASTNode::Boolean(value) => value.to_string(),
+            ASTNode::Nil => "nil".to_string(),
+            ASTNode::Identifier(name, _) => name.clone(),
+            ASTNode::FunctionCall(name, _) => format!("{}!", name),
+            ASTNode::Binary(left, operator, right, _) => {
+                let mut left_text = self.format_expression(left);
+                if left_needs_parens(operator, left) {
+                    left_text = format!("({})", left_text);
+                }
+
+                let mut right_text = self.format_expression(right);
+                if right_needs_parens(operator, right) {
+                    right_text = format!("({})", right_text);
+                }
+
+                format!("{} {} {}", left_text, operator_symbol(operator), right_text)
+            }
+            ASTNode::Unary(operator, right, _) => {
+                let mut right_text = self.format_expression(right);
+                if node_precedence(right) < UNARY {
+                    right_text = format!("({})", right_text);
+                }
+                format!("{}{}", operator_symbol(operator), right_text)
+            }
+            ASTNode::List(elements, _) => format!("[{}]", self.format_list(elements)),
+            ASTNode::Lambda(parameters, body, span) => self.format_lambda(parameters, body, *span),
+            ASTNode::Call(callee, arguments, _) => {
+                let mut callee_text = self.format_expression(callee);
+                if node_precedence(callee) != usize::MAX {
+                    callee_text = format!("({})", callee_text);
+                }
+                format!("{}({})", callee_text, self.format_list(arguments))
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+// binding strength of operators, taken from the levels of the parser: the binary operator
+// table, then unary operators, then ^
+const UNARY: usize = PRECEDENCE.len();
+const POW: usize = UNARY + 1;
+
+fn precedence(operator: &Token) -> usize {
+    if *operator == Token::Pow {
+        return POW;
+    }
+    PRECEDENCE
+        .iter()
+        .position(|operators| operators.contains(operator))
+        .unwrap_or(usize::MAX)
+}
+
+fn node_precedence(ast: &ASTNode) -> usize {
+    match ast {
+        ASTNode::Binary(_, operator, _, _) => precedence(operator),
+        ASTNode::Unary(..) => UNARY,
+        _ => usize::MAX,
+    }
+}
+
+// ^ is right associative and takes a unary base only in parentheses, as in (-a) ^ 2
+fn left_needs_parens(operator: &Token, left: &ASTNode) -> bool {
+    match operator {
+        Token::Pow => node_precedence(left) <= POW,
+        _ => node_precedence(left) < precedence(operator),
+    }
+}
+
+fn right_needs_parens(operator: &Token, right: &ASTNode) -> bool {
+    match operator {
+        Token::Pow => node_precedence(right) < UNARY,
+        _ => node_precedence(right) <= precedence(operator),
+    }
+}
+
+fn operator_symbol(operator: &Token) -> &'static str {
+    match operator {
+        Token::Plus => "+",
+        Token::Minus => "-",
+        Token::Asterisk => "*",
+        Token::Slash => "/",
+        Token::Percent => "%",
+        Token::Pow => "^",
+        Token::EqualEqual => "==",
+        Token::Bang => "!",
+        Token::NotEqual => "!=",
+        Token::LessThan => "<",
+        Token::LessThanEquals => "<=",
+        Token::GreaterThan => ">",
+        Token::GreaterThanEquals => ">=",
+        Token::And => "&&",
+        Token::Or => "||",
+        Token::BitAnd => "&",
+        Token::BitOr => "|",
+        Token::Tilde => "~",
+        Token::QuestionQuestion => "??",
+        Token::ShiftLeft => "<<",
+        Token::ShiftRight => ">>",
+        _ => "?",
+    }
+}
+
+fn annotation_name(annotation: &TypeAnnotation) -> &'static str {
+    match annotation {
+        TypeAnnotation::Int => "int",
+        TypeAnnotation::Str => "str",
+        TypeAnnotation::Bool => "bool",
+    }
+}
+
+fn statement_span(ast: &ASTNode) -> Span {
+    match ast {
+        ASTNode::VariableDeclaration(_, _, _, span)
+        | ASTNode::VariableAssignment(_, _, span)
+        | ASTNode::CompoundAssignment(_, _, _, span)
+        | ASTNode::FunctionCall(_, span)
+        | ASTNode::FunctionDeclaration(_, _, _, span)
+        | ASTNode::Print(_, span)
+        | ASTNode::Return(_, span)
+        | ASTNode::If(_, _, span)
+        | ASTNode::While(_, _, span)
+        | ASTNode::Try(_, _, _, span)
+        | ASTNode::Raise(_, span)
+        | ASTNode::Identifier(_, span)
+        | ASTNode::Binary(_, _, _, span)
+        | ASTNode::Unary(_, _, span) => *span,
+        ASTNode::Program(statements) => statements.first().map(statement_span).unwrap_or_default(),
+        ASTNode::Call(callee, _, _) => statement_span(callee),
+        _ => Span::default(),
+    }
+}
+
+// source text of an expression, as the formatter would write it
+pub fn format_expression(ast: &ASTNode) -> String {
+    Formatter::new("", &[]).format_expression(ast)
+}
+
+pub fn format(code: &str) -> Result<String, Vec<Diagnostic>> {
+    let tokens = tokenize(code).map_err(|error| vec![error.into()])?;
+    let formatter = Formatter::new(code, &tokens);
+    let ast = parse(tokens)?;
+    Ok(formatter.format(&ast))
+}
@@ -1,306 +1,981 @@
-use std::collections::HashMap;
+use std::{
+    cell::RefCell,
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    path::Path,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
-use super::{ast::ASTNode, tokenizer::Token};
+use super::{
+    ast::ASTNode,
+    capabilities::{Capabilities, Capability},
+    environment::Environment,
+    error::{Frame, RuntimeError},
+    formatter::format_expression,
+    limits::Limits,
+    stdlib::NATIVES,
+    tokenizer::{Span, Token},
+    value::{ErrorValue, Function, Value},
+};
 
-struct Interpreter {
-    variables: HashMap<String, ASTNode>,
+// every NETL2 call nests several Rust calls, so the stack is grown on the heap when less
+// than RED_ZONE bytes are left, letting the call depth limit decide when recursion stops
+const RED_ZONE: usize = 64 * 1024;
+const STACK_GROWTH: usize = 1024 * 1024;
+
+// whether execution continues with the next statement or unwinds to the caller
+enum Flow {
+    Next,
+    Return(Value),
+}
+
+// the state of a script after a run, for running several functions from the same start
+pub struct Snapshot {
+    globals: Environment,
+    exit_code: i32,
+}
+
+pub struct Interpreter {
+    // innermost environment of the running function, or of the file outside of functions
+    environment: Rc<RefCell<Environment>>,
+    // calls of NETL2 functions that are still running, outermost first
+    calls: Vec<Frame>,
+    limits: Limits,
+    steps: u64,
+    started: Instant,
+    deadline: Option<Instant>,
+    capabilities: Capabilities,
+    // state of the xorshift generator behind random
+    random: u64,
+    // command-line arguments given to the script
+    arguments: Vec<String>,
+    exit_code: i32,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
-    fn new() -> Self {
+    pub fn new() -> Self {
+        let mut globals = Environment::new();
+        for native in NATIVES {
+            globals.declare(native.name, Value::Native(native));
+        }
+
         Self {
-            variables: HashMap::new(),
+            environment: Rc::new(RefCell::new(globals)),
+            calls: Vec::new(),
+            limits: Limits::default(),
+            steps: 0,
+            started: Instant::now(),
+            deadline: None,
+            capabilities: Capabilities::default(),
+            random: random_seed(),
+            arguments: Vec::new(),
+            exit_code: 0,
+        }
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    pub fn with_arguments(mut self, arguments: Vec<String>) -> Self {
+        self.arguments = arguments;
+        self
+    }
+
+    // the status the process should exit with, 0 unless the script set it or failed
+    pub fn exit_code(&self) -> i32 {
+        self.exit_code
+    }
+
+    pub fn run(&mut self, ast: &ASTNode) -> Result<(), RuntimeError> {
+        self.start();
+        let result = self.interpret(ast).map(|_| ());
+        self.finish(result)
+    }
+
+    // the step count and the timeout start over with every run
+    fn start(&mut self) {
+        self.steps = 0;
+        self.started = Instant::now();
+        self.deadline = self.limits.timeout.map(|timeout| self.started + timeout);
+    }
+
+    // exit is not an error to the embedder, it only sets the exit code
+    fn finish(&mut self, result: Result<(), RuntimeError>) -> Result<(), RuntimeError> {
+        match result {
+            Ok(()) => Ok(()),
+            Err(RuntimeError::Exit(code, _)) => {
+                self.exit_code = code;
+                Ok(())
+            }
+            Err(error) => {
+                self.exit_code = error.exit_code();
+                Err(error)
+            }
+        }
+    }
+
+    // calls a function declared at the top of the script without arguments, after run
+    pub fn run_function(&mut self, name: &str) -> Result<(), RuntimeError> {
+        let function = match self.global(name) {
+            Some(function) => function,
+            None => {
+                return Err(RuntimeError::UndeclaredVariable(
+                    name.to_string(),
+                    Span::default(),
+                ))
+            }
+        };
+        self.start();
+        let result = self.call(&function, Vec::new(), Span::default());
+        self.finish(result.map(|_| ()))
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            globals: self.environment.borrow().clone(),
+            exit_code: self.exit_code,
         }
     }
 
-    fn interpret(&mut self, ast: ASTNode) {
+    // undoes what the functions run since the snapshot did to the globals and exit code
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        *self.environment.borrow_mut() = snapshot.globals.clone();
+        self.exit_code = snapshot.exit_code;
+    }
+
+    // value of a variable or function declared at the top of the script
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.environment.borrow().get(name)
+    }
+
+    fn interpret(&mut self, ast: &ASTNode) -> Result<Flow, RuntimeError> {
+        if !matches!(ast, ASTNode::Program(_)) {
+            self.step(statement_span(ast))?;
+        }
+
         match ast {
             ASTNode::Program(statements) => {
-                for statement in statements.iter() {
-                    self.interpret(statement.clone());
-                }
+                return self.interpret_block(statements);
             }
-            ASTNode::VariableDeclaration(name, value) => {
-                self.variables.insert(name, *value);
+            ASTNode::VariableDeclaration(name, _, value, _) => {
+                let evaluated_value = self.evaluate_expression(value)?;
+                self.environment.borrow_mut().declare(name, evaluated_value);
             }
-            ASTNode::VariableAssignment(name, value) => {
-                let evaluated_value = self.evaluate_expression(*value);
-                if let Some(variable) = self.variables.get_mut(&name) {
-                    // eval value
-                    *variable = evaluated_value;
-                } else {
-                    panic!("Variable {} not found", name);
+            ASTNode::VariableAssignment(name, value, span) => {
+                let evaluated_value = self.evaluate_expression(value)?;
+                if !self.environment.borrow_mut().assign(name, evaluated_value) {
+                    return Err(RuntimeError::UndeclaredVariable(name.clone(), *span));
                 }
             }
-            ASTNode::Identifier(name) => {
-                if let Some(value) = self.variables.get(&name) {
-                    self.interpret(value.clone());
-                } else {
-                    panic!("Variable {} not found", name);
-                }
+            ASTNode::CompoundAssignment(name, operator, value, span) => {
+                let current = self.lookup(name, *span)?;
+                let evaluated_value = self.evaluate_expression(value)?;
+                let result =
+                    self.evaluate_binary_operation(current, operator, evaluated_value, *span)?;
+                self.environment.borrow_mut().assign(name, result);
             }
-            ASTNode::FunctionCall(name) => {
-                if let Some(value) = self.variables.get(&name) {
-                    let program = match value.clone() {
-                        ASTNode::FunctionDeclaration(_, body) => ASTNode::Program(Box::new(body)),
-                        _ => panic!("Unexpected ASTNode: {:?}", value),
-                    };
-                    self.interpret(program);
-                } else {
-                    panic!("Function {} not found", name);
-                }
+            ASTNode::FunctionCall(name, span) => {
+                let function = self.lookup(name, *span)?;
+                // the returned value has nowhere to go when called as a statement
+                self.call(&function, Vec::new(), *span)?;
             }
-            ASTNode::FunctionDeclaration(name, body) => {
-                self.variables
-                    .insert(name.clone(), ASTNode::FunctionDeclaration(name, body));
+            ASTNode::Call(callee, arguments, span) => {
+                self.evaluate_call(callee, arguments, *span)?;
             }
-            ASTNode::Print(expression) => {
-                let evaluated_expression = self.evaluate_expression(*expression);
-                println!("{}", self.stringify_value(evaluated_expression));
+            ASTNode::FunctionDeclaration(name, parameters, body, _) => {
+                let function = self.function(Some(name.clone()), parameters, body);
+                self.environment.borrow_mut().declare(name, function);
             }
-            ASTNode::If(expression, statements) => {
-                let evaluated_expression = self.evaluate_expression(*expression);
+            ASTNode::Print(expression, span) => {
+                self.require("p", Capability::Stdout, *span)?;
+                let evaluated_expression = self.evaluate_expression(expression)?;
+                println!("{}", self.stringify_value(&evaluated_expression));
+            }
+            ASTNode::If(expression, statements, _) => {
+                let evaluated_expression = self.evaluate_expression(expression)?;
 
-                if self.stringify_value(evaluated_expression) == "true" {
-                    for statement in statements.iter() {
-                        self.interpret(statement.clone());
-                    }
+                if self.is_true(&evaluated_expression) {
+                    return self.interpret_block(statements);
                 }
             }
-            ASTNode::While(expression, statements) => {
+            ASTNode::While(expression, statements, span) => {
                 // check if expression is true, if so, interpret statements and re-evaluate expression
-                let mut evaluated_expression = self.evaluate_expression(*expression.clone());
+                let mut evaluated_expression = self.evaluate_expression(expression)?;
 
-                while self.stringify_value(evaluated_expression) == "true" {
-                    for statement in statements.iter() {
-                        self.interpret(statement.clone());
+                while self.is_true(&evaluated_expression) {
+                    self.step(*span)?;
+                    if let Flow::Return(value) = self.interpret_block(statements)? {
+                        return Ok(Flow::Return(value));
                     }
-                    evaluated_expression = self.evaluate_expression(*expression.clone());
+                    evaluated_expression = self.evaluate_expression(expression)?;
                 }
             }
+            ASTNode::Return(expression, _) => {
+                let value = match expression {
+                    Some(expression) => self.evaluate_expression(expression)?,
+                    None => Value::Nil,
+                };
+                return Ok(Flow::Return(value));
+            }
+            ASTNode::Try(statements, name, handler, _) => {
+                let environment = self.environment.clone();
+                let depth = self.calls.len();
+
+                match self.interpret_block(statements) {
+                    Err(error) if error.is_catchable() => {
+                        // calls restore both on the way out already, this only makes sure
+                        // the handler runs exactly where the t statement started
+                        self.environment = environment;
+                        self.calls.truncate(depth);
+
+                        let error = Value::error(error.kind(), error.message());
+                        self.environment.borrow_mut().declare(name, error);
+                        return self.interpret_block(handler);
+                    }
+                    flow => return flow,
+                }
+            }
+            ASTNode::Raise(expression, span) => {
+                let error = match self.evaluate_expression(expression)? {
+                    Value::String(message) => ErrorValue {
+                        kind: "error".to_string(),
+                        message,
+                    },
+                    Value::Error(error) => error.as_ref().clone(),
+                    value => {
+                        return Err(RuntimeError::InvalidValue(
+                            format!("e expects a str or an error but got {}", value.type_name()),
+                            *span,
+                        ))
+                    }
+                };
+                return Err(RuntimeError::Raised(error, *span));
+            }
+            // left behind by parse_partial where a statement could not be parsed
+            ASTNode::Error(span) => {
+                return Err(RuntimeError::InvalidValue(
+                    "Cannot run a statement that failed to parse".to_string(),
+                    *span,
+                ))
+            }
             _ => panic!("Unexpected ASTNode: {:?}", ast),
         }
+
+        Ok(Flow::Next)
     }
 
-    fn stringify_value(&mut self, ast: ASTNode) -> String {
-        match ast {
-            ASTNode::Number(value) => value.to_string(),
-            ASTNode::String(value) => value,
-            ASTNode::Boolean(value) => value.to_string(),
-            _ => panic!("Unexpected AST node: {:?}", ast),
+    fn interpret_block(&mut self, statements: &[ASTNode]) -> Result<Flow, RuntimeError> {
+        for statement in statements {
+            if let Flow::Return(value) = self.interpret(statement)? {
+                return Ok(Flow::Return(value));
+            }
         }
+
+        Ok(Flow::Next)
     }
 
-    fn evaluate_expression(&mut self, ast: ASTNode) -> ASTNode {
-        match ast {
-            ASTNode::Binary(left, operator, right) => {
-                let left_val = self.evaluate_expression(*left);
-                let right_val = self.evaluate_expression(*right);
-                self.evaluate_binary_operation(left_val, operator, right_val)
+    // limits
+
+    fn step(&mut self, span: Span) -> Result<(), RuntimeError> {
+        self.steps += 1;
+        if let Some(max_steps) = self.limits.max_steps {
+            if self.steps > max_steps {
+                return Err(RuntimeError::StepLimit(max_steps, span));
+            }
+        }
+
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                let timeout = self.limits.timeout.unwrap_or(Duration::ZERO);
+                return Err(RuntimeError::Timeout(timeout, span));
+            }
+        }
+        Ok(())
+    }
+
+    // called before a string or list of the given size is created or grown; values that
+    // are no longer used are freed, so only the size of each one is limited
+    pub(super) fn allocate(&self, bytes: usize, span: Span) -> Result<(), RuntimeError> {
+        match self.limits.max_memory {
+            Some(max_memory) if bytes > max_memory => {
+                Err(RuntimeError::MemoryLimit(max_memory, span))
             }
-            ASTNode::Unary(operator, right) => {
-                let right_val = self.evaluate_expression(*right);
-                self.evaluate_unary_operation(operator, right_val)
+            _ => Ok(()),
+        }
+    }
+
+    // capabilities
+
+    fn require(&self, name: &str, capability: Capability, span: Span) -> Result<(), RuntimeError> {
+        if self.capabilities.allows(capability) {
+            Ok(())
+        } else {
+            Err(RuntimeError::PermissionDenied(
+                name.to_string(),
+                capability,
+                span,
+            ))
+        }
+    }
+
+    // file system access can be limited to some directories
+    pub(super) fn require_path(
+        &self,
+        capability: Capability,
+        path: &str,
+        span: Span,
+    ) -> Result<(), RuntimeError> {
+        if self.capabilities.allows_path(capability, Path::new(path)) {
+            Ok(())
+        } else {
+            Err(RuntimeError::PermissionDenied(
+                path.to_string(),
+                capability,
+                span,
+            ))
+        }
+    }
+
+    pub(super) fn arguments(&self) -> &[String] {
+        &self.arguments
+    }
+
+    pub(super) fn set_exit_code(&mut self, code: i32) {
+        self.exit_code = code;
+    }
+
+    pub(super) fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    pub(super) fn next_random(&mut self) -> u64 {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 7;
+        self.random ^= self.random << 17;
+        self.random
+    }
+
+    pub(super) fn stringify_value(&self, value: &Value) -> String {
+        match value {
+            Value::Number(value) => value.to_string(),
+            Value::String(value) => value.clone(),
+            Value::Boolean(value) => value.to_string(),
+            Value::Nil => "nil".to_string(),
+            Value::List(values) => {
+                let values: Vec<String> = values
+                    .iter()
+                    .map(|value| self.describe_value(value))
+                    .collect();
+                format!("[{}]", values.join(", "))
             }
-            ASTNode::Boolean(value) => ASTNode::Boolean(value),
-            ASTNode::String(value) => ASTNode::String(value),
-            ASTNode::Number(value) => ASTNode::Number(value),
-            ASTNode::Identifier(name) => {
-                if let Some(value) = self.variables.get(&name) {
-                    self.evaluate_expression(value.clone())
-                } else {
-                    panic!("Variable {} not found", name);
+            Value::Function(function) => match &function.name {
+                Some(name) => format!("<function {}>", name),
+                None => "<function>".to_string(),
+            },
+            Value::Native(native) => format!("<function {}>", native.name),
+            Value::Error(error) => format!("<error {}: {}>", error.kind, error.message),
+        }
+    }
+
+    // like stringify_value, but with strings in quotes so "1" and 1 can be told apart
+    pub(super) fn describe_value(&self, value: &Value) -> String {
+        match value {
+            Value::String(value) => format!("{:?}", value),
+            value => self.stringify_value(value),
+        }
+    }
+
+    // conditions accept whatever stringifies to "true", matching how they were checked
+    // before, so nil counts as false
+    pub(super) fn is_true(&self, value: &Value) -> bool {
+        match value {
+            Value::Boolean(value) => *value,
+            Value::String(value) => value == "true",
+            _ => false,
+        }
+    }
+
+    // functions
+
+    fn function(
+        &self,
+        name: Option<String>,
+        parameters: &[String],
+        body: &Rc<Vec<ASTNode>>,
+    ) -> Value {
+        Value::Function(Rc::new(Function {
+            name,
+            parameters: parameters.to_vec(),
+            body: body.clone(),
+            environment: self.environment.clone(),
+        }))
+    }
+
+    fn evaluate_call(
+        &mut self,
+        callee: &ASTNode,
+        arguments: &[ASTNode],
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        let function = self.evaluate_expression(callee)?;
+        let values = arguments
+            .iter()
+            .map(|argument| self.evaluate_expression(argument))
+            .collect::<Result<_, _>>()?;
+
+        // assertions only see values, so the source of what they checked is added here
+        match self.call(&function, values, span) {
+            Err(RuntimeError::AssertionFailed(None, values, span)) if matches!(function, Value::Native(native) if native.assertion) =>
+            {
+                let expression = match arguments {
+                    [left, right] => format!(
+                        "{} == {}",
+                        format_expression(left),
+                        format_expression(right)
+                    ),
+                    arguments => arguments.iter().map(format_expression).collect(),
+                };
+                Err(RuntimeError::AssertionFailed(
+                    Some(expression),
+                    values,
+                    span,
+                ))
+            }
+            result => result,
+        }
+    }
+
+    // runs a function in a new environment inside the one it was declared in
+    pub(super) fn call(
+        &mut self,
+        function: &Value,
+        arguments: Vec<Value>,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match function {
+            Value::Function(function) => {
+                let name = function_name(function);
+                check_arity(&name, function.parameters.len(), arguments.len(), span)?;
+
+                let frame = Frame {
+                    function: name,
+                    span,
+                };
+                if self.calls.len() >= self.limits.max_depth {
+                    let mut frames = self.calls.clone();
+                    frames.push(frame);
+                    return Err(RuntimeError::StackOverflow(frames));
+                }
+
+                let mut environment = Environment::with_parent(function.environment.clone());
+                for (parameter, argument) in function.parameters.iter().zip(arguments) {
+                    environment.declare(parameter, argument);
+                }
+
+                // the caller's environment and call chain are restored even if the call fails
+                let caller =
+                    std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+                self.calls.push(frame);
+                let flow = stacker::maybe_grow(RED_ZONE, STACK_GROWTH, || {
+                    self.interpret_block(&function.body)
+                });
+                self.calls.pop();
+                self.environment = caller;
+
+                match flow? {
+                    Flow::Return(value) => Ok(value),
+                    // without an r the function returns nil
+                    Flow::Next => Ok(Value::Nil),
+                }
+            }
+            Value::Native(native) => {
+                if let Some(capability) = native.capability {
+                    self.require(native.name, capability, span)?;
                 }
+                check_arity(native.name, native.arity, arguments.len(), span)?;
+                (native.function)(self, arguments, span)
             }
-            ASTNode::FunctionCall(name) => {
-                if let Some(value) = self.variables.get(&name) {
-                    self.evaluate_expression(value.clone())
-                } else {
-                    panic!("Function {} not found", name);
+            value => Err(RuntimeError::NotCallable(value.type_name(), span)),
+        }
+    }
+
+    fn lookup(&self, name: &str, span: Span) -> Result<Value, RuntimeError> {
+        match self.environment.borrow().get(name) {
+            Some(value) => Ok(value),
+            None => Err(RuntimeError::UndeclaredVariable(name.to_string(), span)),
+        }
+    }
+
+    // expressions
+
+    fn evaluate_expression(&mut self, ast: &ASTNode) -> Result<Value, RuntimeError> {
+        match ast {
+            ASTNode::Binary(left, operator, right, span) => {
+                let left_val = self.evaluate_expression(left)?;
+                // && and || skip their right operand once the left one decides the result
+                match (operator, &left_val) {
+                    (Token::And, Value::Boolean(false)) => return Ok(Value::Boolean(false)),
+                    (Token::Or, Value::Boolean(true)) => return Ok(Value::Boolean(true)),
+                    // ?? only evaluates its default when the left operand is nil
+                    (Token::QuestionQuestion, Value::Nil) => {
+                        return self.evaluate_expression(right)
+                    }
+                    (Token::QuestionQuestion, _) => return Ok(left_val),
+                    _ => {}
                 }
+                let right_val = self.evaluate_expression(right)?;
+                self.evaluate_binary_operation(left_val, operator, right_val, *span)
+            }
+            ASTNode::Unary(operator, right, span) => {
+                let right_val = self.evaluate_expression(right)?;
+                self.evaluate_unary_operation(operator, right_val, *span)
             }
+            ASTNode::Boolean(value) => Ok(Value::Boolean(*value)),
+            ASTNode::String(value) => Ok(Value::String(value.clone())),
+            ASTNode::Number(value) => Ok(Value::Number(*value)),
+            ASTNode::Identifier(name, span) => self.lookup(name, *span),
+            ASTNode::List(elements, span) => {
+                self.allocate(list_size(elements.len()), *span)?;
+                let elements = elements
+                    .iter()
+                    .map(|element| self.evaluate_expression(element))
+                    .collect::<Result<_, _>>()?;
+                Ok(Value::List(Rc::new(elements)))
+            }
+            ASTNode::Lambda(parameters, body, _) => Ok(self.function(None, parameters, body)),
+            ASTNode::Nil => Ok(Value::Nil),
+            ASTNode::Call(callee, arguments, span) => self.evaluate_call(callee, arguments, *span),
             _ => panic!("Unexpected ASTNode: {:?}", ast),
         }
     }
 
     fn evaluate_binary_operation(
         &mut self,
-        left: ASTNode,
-        operator: Token,
-        right: ASTNode,
-    ) -> ASTNode {
+        left: Value,
+        operator: &Token,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
         match operator {
-            Token::Plus => self.evaluate_addition(left, right),
-            Token::Minus => self.evaluate_subtraction(left, right),
-            Token::Asterisk => self.evaluate_multiplication(left, right),
-            Token::Slash => self.evaluate_division(left, right),
-            Token::Percent => self.evaluate_modulo(left, right),
-            Token::Pow => self.evaluate_pow(left, right),
-            Token::Equals => self.evaluate_equal(left, right),
-            Token::EqualEqual => self.evaluate_equal_equal(left, right),
-            Token::Bang => self.evaluate_not_equal(left, right),
-            Token::NotEqual => self.evaluate_not_equal(left, right),
-            Token::LessThan => self.evaluate_less_than(left, right),
-            Token::LessThanEquals => self.evaluate_less_than_equals(left, right),
-            Token::GreaterThan => self.evaluate_greater_than(left, right),
-            Token::GreaterThanEquals => self.evaluate_greater_than_equals(left, right),
-            Token::And => self.evaluate_and(left, right),
-            Token::Or => self.evaluate_or(left, right),
+            Token::Plus => self.evaluate_addition(left, right, span),
+            Token::Minus => self.evaluate_subtraction(left, right, span),
+            Token::Asterisk => self.evaluate_multiplication(left, right, span),
+            Token::Slash => self.evaluate_division(left, right, span),
+            Token::Percent => self.evaluate_modulo(left, right, span),
+            Token::Pow => self.evaluate_pow(left, right, span),
+            Token::EqualEqual => self.evaluate_equal_equal(left, right, span),
+            Token::Bang => self.evaluate_not_equal(left, right, span),
+            Token::NotEqual => self.evaluate_not_equal(left, right, span),
+            Token::LessThan => self.evaluate_less_than(left, right, span),
+            Token::LessThanEquals => self.evaluate_less_than_equals(left, right, span),
+            Token::GreaterThan => self.evaluate_greater_than(left, right, span),
+            Token::GreaterThanEquals => self.evaluate_greater_than_equals(left, right, span),
+            Token::And => self.evaluate_and(left, right, span),
+            Token::Or => self.evaluate_or(left, right, span),
+            Token::BitAnd => self.evaluate_bit_and(left, right, span),
+            Token::BitOr => self.evaluate_bit_or(left, right, span),
+            Token::Tilde => self.evaluate_xor(left, right, span),
+            Token::ShiftLeft => self.evaluate_shift_left(left, right, span),
+            Token::ShiftRight => self.evaluate_shift_right(left, right, span),
             _ => panic!("Unexpected operator: {:?}", operator),
         }
     }
 
-    fn evaluate_unary_operation(&mut self, operator: Token, right: ASTNode) -> ASTNode {
+    fn evaluate_unary_operation(
+        &mut self,
+        operator: &Token,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
         match operator {
-            Token::Minus => self.evaluate_negation(right),
-            Token::Bang => self.evaluate_not(right),
+            Token::Minus => self.evaluate_negation(right, span),
+            Token::Bang => self.evaluate_not(right, span),
+            Token::Tilde => self.evaluate_bit_not(right, span),
             _ => panic!("Unexpected operator: {:?}", operator),
         }
     }
 
     // binary operations
 
-    fn evaluate_addition(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Number(left), ASTNode::Number(right)) => ASTNode::Number(left + right),
-            (ASTNode::String(left), ASTNode::String(right)) => {
-                ASTNode::String(format!("{}{}", left, right))
+    fn evaluate_addition(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => left
+                .checked_add(*right)
+                .map(Value::Number)
+                .ok_or(RuntimeError::Overflow(span)),
+            (Value::String(left), Value::String(right)) => {
+                self.allocate(left.len() + right.len(), span)?;
+                Ok(Value::String(format!("{}{}", left, right)))
             }
-            _ => panic!("Unexpected operands: {:?} + {:?}", left, right),
+            _ => Err(invalid_operands("+", &[left, right], span)),
         }
     }
 
-    fn evaluate_subtraction(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Number(left), ASTNode::Number(right)) => ASTNode::Number(left - right),
-            _ => panic!("Unexpected operands: {:?} - {:?}", left, right),
+    fn evaluate_subtraction(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => left
+                .checked_sub(*right)
+                .map(Value::Number)
+                .ok_or(RuntimeError::Overflow(span)),
+            _ => Err(invalid_operands("-", &[left, right], span)),
         }
     }
 
-    fn evaluate_multiplication(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Number(left), ASTNode::Number(right)) => ASTNode::Number(left * right),
-            _ => panic!("Unexpected operands: {:?} * {:?}", left, right),
+    fn evaluate_multiplication(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => left
+                .checked_mul(*right)
+                .map(Value::Number)
+                .ok_or(RuntimeError::Overflow(span)),
+            _ => Err(invalid_operands("*", &[left, right], span)),
         }
     }
 
-    fn evaluate_division(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Number(left), ASTNode::Number(right)) => ASTNode::Number(left / right),
-            _ => panic!("Unexpected operands: {:?} / {:?}", left, right),
+    fn evaluate_division(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(_), Value::Number(0)) => Err(RuntimeError::DivisionByZero(span)),
+            (Value::Number(left), Value::Number(right)) => left
+                .checked_div(*right)
+                .map(Value::Number)
+                .ok_or(RuntimeError::Overflow(span)),
+            _ => Err(invalid_operands("/", &[left, right], span)),
         }
     }
 
-    fn evaluate_modulo(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Number(left), ASTNode::Number(right)) => ASTNode::Number(left % right),
-            _ => panic!("Unexpected operands: {:?} % {:?}", left, right),
+    fn evaluate_modulo(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(_), Value::Number(0)) => Err(RuntimeError::DivisionByZero(span)),
+            (Value::Number(left), Value::Number(right)) => left
+                .checked_rem(*right)
+                .map(Value::Number)
+                .ok_or(RuntimeError::Overflow(span)),
+            _ => Err(invalid_operands("%", &[left, right], span)),
         }
     }
 
-    fn evaluate_pow(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Number(left), ASTNode::Number(right)) => ASTNode::Number(left.pow(right as u32)),
-            _ => panic!("Unexpected operands: {:?} ^ {:?}", left, right),
+    fn evaluate_pow(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => match u32::try_from(*right) {
+                Ok(exponent) => left
+                    .checked_pow(exponent)
+                    .map(Value::Number)
+                    .ok_or(RuntimeError::Overflow(span)),
+                Err(_) => Err(RuntimeError::InvalidValue(
+                    format!("Exponent {} is negative", right),
+                    span,
+                )),
+            },
+            _ => Err(invalid_operands("^", &[left, right], span)),
         }
     }
 
-    fn evaluate_equal(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Identifier(left), ASTNode::Identifier(right)) => {
-                ASTNode::Boolean(left == right)
-            }
-            (ASTNode::Number(left), ASTNode::Number(right)) => ASTNode::Boolean(left == right),
-            (ASTNode::String(left), ASTNode::String(right)) => ASTNode::Boolean(left == right),
-            (ASTNode::Boolean(left), ASTNode::Boolean(right)) => ASTNode::Boolean(left == right),
-            _ => panic!("Unexpected operands: {:?} == {:?}", left, right),
+    fn evaluate_equal_equal(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => Ok(Value::Boolean(left == right)),
+            (Value::String(left), Value::String(right)) => Ok(Value::Boolean(left == right)),
+            (Value::Boolean(left), Value::Boolean(right)) => Ok(Value::Boolean(left == right)),
+            // anything can be compared with nil to check for a missing value
+            (Value::Nil, _) | (_, Value::Nil) => Ok(Value::Boolean(left == right)),
+            _ => Err(invalid_operands("==", &[left, right], span)),
         }
     }
 
-    fn evaluate_equal_equal(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Identifier(left), ASTNode::Identifier(right)) => {
-                ASTNode::Boolean(left == right)
-            }
-            (ASTNode::Number(left), ASTNode::Number(right)) => ASTNode::Boolean(left == right),
-            (ASTNode::String(left), ASTNode::String(right)) => ASTNode::Boolean(left == right),
-            (ASTNode::Boolean(left), ASTNode::Boolean(right)) => ASTNode::Boolean(left == right),
-            _ => panic!("Unexpected operands: {:?} == {:?}", left, right),
+    fn evaluate_not_equal(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => Ok(Value::Boolean(left != right)),
+            (Value::String(left), Value::String(right)) => Ok(Value::Boolean(left != right)),
+            (Value::Boolean(left), Value::Boolean(right)) => Ok(Value::Boolean(left != right)),
+            // anything can be compared with nil to check for a missing value
+            (Value::Nil, _) | (_, Value::Nil) => Ok(Value::Boolean(left != right)),
+            _ => Err(invalid_operands("!=", &[left, right], span)),
         }
     }
 
-    fn evaluate_not_equal(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Identifier(left), ASTNode::Identifier(right)) => {
-                ASTNode::Boolean(left != right)
-            }
-            (ASTNode::Number(left), ASTNode::Number(right)) => ASTNode::Boolean(left != right),
-            (ASTNode::String(left), ASTNode::String(right)) => ASTNode::Boolean(left != right),
-            (ASTNode::Boolean(left), ASTNode::Boolean(right)) => ASTNode::Boolean(left != right),
-            _ => panic!("Unexpected operands: {:?} != {:?}", left, right),
+    fn evaluate_less_than(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => Ok(Value::Boolean(left < right)),
+            _ => Err(invalid_operands("<", &[left, right], span)),
+        }
+    }
+
+    fn evaluate_less_than_equals(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => Ok(Value::Boolean(left <= right)),
+            _ => Err(invalid_operands("<=", &[left, right], span)),
+        }
+    }
+
+    fn evaluate_greater_than(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => Ok(Value::Boolean(left > right)),
+            _ => Err(invalid_operands(">", &[left, right], span)),
         }
     }
 
-    fn evaluate_less_than(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Number(left), ASTNode::Number(right)) => ASTNode::Boolean(left < right),
-            _ => panic!("Unexpected operands: {:?} < {:?}", left, right),
+    fn evaluate_greater_than_equals(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => Ok(Value::Boolean(left >= right)),
+            _ => Err(invalid_operands(">=", &[left, right], span)),
         }
     }
 
-    fn evaluate_less_than_equals(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Number(left), ASTNode::Number(right)) => ASTNode::Boolean(left <= right),
-            _ => panic!("Unexpected operands: {:?} <= {:?}", left, right),
+    fn evaluate_and(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Boolean(left), Value::Boolean(right)) => Ok(Value::Boolean(*left && *right)),
+            _ => Err(invalid_operands("&&", &[left, right], span)),
         }
     }
 
-    fn evaluate_greater_than(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Number(left), ASTNode::Number(right)) => ASTNode::Boolean(left > right),
-            _ => panic!("Unexpected operands: {:?} > {:?}", left, right),
+    fn evaluate_or(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Boolean(left), Value::Boolean(right)) => Ok(Value::Boolean(*left || *right)),
+            _ => Err(invalid_operands("||", &[left, right], span)),
         }
     }
 
-    fn evaluate_greater_than_equals(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Number(left), ASTNode::Number(right)) => ASTNode::Boolean(left >= right),
-            _ => panic!("Unexpected operands: {:?} >= {:?}", left, right),
+    fn evaluate_bit_and(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left & right)),
+            _ => Err(invalid_operands("&", &[left, right], span)),
         }
     }
 
-    fn evaluate_and(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Boolean(left), ASTNode::Boolean(right)) => ASTNode::Boolean(left && right),
-            _ => panic!("Unexpected operands: {:?} && {:?}", left, right),
+    fn evaluate_bit_or(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left | right)),
+            _ => Err(invalid_operands("|", &[left, right], span)),
         }
     }
 
-    fn evaluate_or(&mut self, left: ASTNode, right: ASTNode) -> ASTNode {
-        match (left.clone(), right.clone()) {
-            (ASTNode::Boolean(left), ASTNode::Boolean(right)) => ASTNode::Boolean(left || right),
-            _ => panic!("Unexpected operands: {:?} || {:?}", left, right),
+    fn evaluate_xor(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => Ok(Value::Number(left ^ right)),
+            _ => Err(invalid_operands("~", &[left, right], span)),
+        }
+    }
+
+    fn evaluate_shift_left(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => match u32::try_from(*right)
+                .ok()
+                .and_then(|right| left.checked_shl(right))
+            {
+                Some(value) => Ok(Value::Number(value)),
+                None => Err(RuntimeError::InvalidValue(
+                    format!("Shift amount {} is out of range", right),
+                    span,
+                )),
+            },
+            _ => Err(invalid_operands("<<", &[left, right], span)),
+        }
+    }
+
+    // shifts are arithmetic, so negative numbers keep their sign
+    fn evaluate_shift_right(
+        &mut self,
+        left: Value,
+        right: Value,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        match (&left, &right) {
+            (Value::Number(left), Value::Number(right)) => match u32::try_from(*right)
+                .ok()
+                .and_then(|right| left.checked_shr(right))
+            {
+                Some(value) => Ok(Value::Number(value)),
+                None => Err(RuntimeError::InvalidValue(
+                    format!("Shift amount {} is out of range", right),
+                    span,
+                )),
+            },
+            _ => Err(invalid_operands(">>", &[left, right], span)),
         }
     }
 
     // unary operations
 
-    fn evaluate_negation(&mut self, right: ASTNode) -> ASTNode {
+    fn evaluate_negation(&mut self, right: Value, span: Span) -> Result<Value, RuntimeError> {
         match right {
-            ASTNode::Number(right) => ASTNode::Number(-right),
-            _ => panic!("Unexpected operand: -{:?}", right),
+            Value::Number(right) => right
+                .checked_neg()
+                .map(Value::Number)
+                .ok_or(RuntimeError::Overflow(span)),
+            _ => Err(invalid_operands("-", &[right], span)),
         }
     }
 
-    fn evaluate_not(&mut self, right: ASTNode) -> ASTNode {
+    fn evaluate_not(&mut self, right: Value, span: Span) -> Result<Value, RuntimeError> {
         match right {
-            ASTNode::Boolean(right) => ASTNode::Boolean(!right),
-            _ => panic!("Unexpected operand: !{:?}", right),
+            Value::Boolean(right) => Ok(Value::Boolean(!right)),
+            _ => Err(invalid_operands("!", &[right], span)),
         }
     }
+
+    fn evaluate_bit_not(&mut self, right: Value, span: Span) -> Result<Value, RuntimeError> {
+        match right {
+            Value::Number(right) => Ok(Value::Number(!right)),
+            _ => Err(invalid_operands("~", &[right], span)),
+        }
+    }
+}
+
+fn function_name(function: &Function) -> String {
+    function
+        .name
+        .clone()
+        .unwrap_or_else(|| "function".to_string())
+}
+
+// std has no random number generator, but its hash keys are seeded randomly per process
+fn random_seed() -> u64 {
+    let seed = RandomState::new().build_hasher().finish();
+    // xorshift never leaves a zero state
+    seed | 1
+}
+
+pub(super) fn list_size(length: usize) -> usize {
+    length.saturating_mul(std::mem::size_of::<Value>())
+}
+
+fn statement_span(ast: &ASTNode) -> Span {
+    match ast {
+        ASTNode::VariableDeclaration(_, _, _, span)
+        | ASTNode::VariableAssignment(_, _, span)
+        | ASTNode::CompoundAssignment(_, _, _, span)
+        | ASTNode::FunctionCall(_, span)
+        | ASTNode::FunctionDeclaration(_, _, _, span)
+        | ASTNode::Call(_, _, span)
+        | ASTNode::Print(_, span)
+        | ASTNode::Return(_, span)
+        | ASTNode::If(_, _, span)
+        | ASTNode::While(_, _, span)
+        | ASTNode::Try(_, _, _, span)
+        | ASTNode::Raise(_, span) => *span,
+        _ => Span::default(),
+    }
+}
+
+fn check_arity(name: &str, expected: usize, actual: usize, span: Span) -> Result<(), RuntimeError> {
+    if expected != actual {
+        return Err(RuntimeError::WrongArgumentCount(
+            name.to_string(),
+            expected,
+            actual,
+            span,
+        ));
+    }
+    Ok(())
+}
+
+fn invalid_operands(operator: &str, operands: &[Value], span: Span) -> RuntimeError {
+    let types = operands.iter().map(Value::type_name).collect();
+    RuntimeError::InvalidOperands(operator.to_string(), types, span)
 }
 
-pub fn interpret(ast: ASTNode) {
-    let mut interpreter = Interpreter::new();
-    interpreter.interpret(ast);
+pub fn interpret(ast: &ASTNode) -> Result<(), RuntimeError> {
+    Interpreter::new().run(ast)
 }
@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+// Bounds on what a script may use, so untrusted scripts cannot hang or exhaust the host.
+// Only the call depth is limited by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    // function calls running at once
+    pub max_depth: usize,
+    // statements and loop iterations executed
+    pub max_steps: Option<u64>,
+    // wall-clock time from the start of the script
+    pub timeout: Option<Duration>,
+    // largest string or list a script can create, in bytes
+    pub max_memory: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_steps: None,
+            timeout: None,
+            max_memory: None,
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    ast::{parse, ASTNode},
+    diagnostic::Diagnostic,
+    tester::TEST_PREFIX,
+    tokenizer::{tokenize, Span, Token},
+};
+
+// rule IDs are part of the suppression syntax, so they must never change
+pub const NAMING: &str = "naming";
+pub const UNUSED_VARIABLE: &str = "unused-variable";
+pub const UNUSED_FUNCTION: &str = "unused-function";
+pub const UNREACHABLE_CODE: &str = "unreachable-code";
+pub const INFINITE_LOOP: &str = "infinite-loop";
+pub const SELF_ASSIGNMENT: &str = "self-assignment";
+
+const SUPPRESSION_PREFIX: &str = "lint:allow(";
+
+struct Linter {
+    // first declaration of every variable and function
+    variables: Vec<(String, Span)>,
+    functions: Vec<(String, Span)>,
+    read: HashSet<String>,
+    called: HashSet<String>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter {
+    fn new() -> Self {
+        Self {
+            variables: Vec::new(),
+            functions: Vec::new(),
+            read: HashSet::new(),
+            called: HashSet::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn lint(mut self, ast: &ASTNode) -> Vec<Diagnostic> {
+        self.lint_statement(ast);
+
+        for (name, span) in &self.variables {
+            if !self.read.contains(name) && !name.starts_with('_') {
+                self.diagnostics.push(
+                    Diagnostic::warning(format!("Variable {} is never read", name), *span)
+                        .with_code(UNUSED_VARIABLE),
+                );
+            }
+        }
+        for (name, span) in &self.functions {
+            // a function passed around as a value counts as used, and tests are called by
+            // the test runner
+            if !self.called.contains(name)
+                && !self.read.contains(name)
+                && !name.starts_with('_')
+                && !name.starts_with(TEST_PREFIX)
+            {
+                self.diagnostics.push(
+                    Diagnostic::warning(format!("Function {} is never called", name), *span)
+                        .with_code(UNUSED_FUNCTION),
+                );
+            }
+        }
+
+        self.diagnostics.sort_by_key(|diagnostic| diagnostic.span);
+        self.diagnostics
+    }
+
+    // statements
+
+    fn lint_statement(&mut self, ast: &ASTNode) {
+        match ast {
+            ASTNode::Program(statements) => self.lint_block(statements),
+            ASTNode::VariableDeclaration(name, _, value, span) => {
+                self.lint_expression(value);
+                self.check_naming("Variable", name, *span);
+                if !self.variables.iter().any(|(declared, _)| declared == name) {
+                    self.variables.push((name.clone(), *span));
+                }
+            }
+            ASTNode::VariableAssignment(name, value, span) => {
+                if let ASTNode::Identifier(assigned, _) = value.as_ref() {
+                    if assigned == name {
+                        self.diagnostics.push(
+                            Diagnostic::warning(format!("{} is assigned to itself", name), *span)
+                                .with_code(SELF_ASSIGNMENT),
+                        );
+                        return;
+                    }
+                }
+                self.lint_expression(value);
+            }
+            // the variable is only read to be written back, so it does not count as a use
+            ASTNode::CompoundAssignment(_, _, value, _) => self.lint_expression(value),
+            ASTNode::FunctionDeclaration(name, _, body, span) => {
+                self.check_naming("Function", name, *span);
+                if !self.functions.iter().any(|(declared, _)| declared == name) {
+                    self.functions.push((name.clone(), *span));
+                }
+                self.lint_block(body);
+            }
+            // the name may also be a variable holding a function
+            ASTNode::FunctionCall(name, _) => {
+                self.called.insert(name.clone());
+                self.read.insert(name.clone());
+            }
+            ASTNode::Print(expression, _) | ASTNode::Return(Some(expression), _) => {
+                self.lint_expression(expression);
+            }
+            ASTNode::If(condition, statements, _) => {
+                self.lint_expression(condition);
+                self.lint_block(statements);
+            }
+            ASTNode::While(condition, statements, span) => {
+                self.lint_expression(condition);
+                if matches!(condition.as_ref(), ASTNode::Boolean(true))
+                    && !returns(statements, false)
+                {
+                    self.diagnostics.push(
+                        Diagnostic::warning("Loop never exits".to_string(), *span)
+                            .with_code(INFINITE_LOOP),
+                    );
+                }
+                self.lint_block(statements);
+            }
+            ASTNode::Try(statements, name, handler, span) => {
+                self.lint_block(statements);
+                self.check_naming("Variable", name, *span);
+                if !self.variables.iter().any(|(declared, _)| declared == name) {
+                    self.variables.push((name.clone(), *span));
+                }
+                self.lint_block(handler);
+            }
+            ASTNode::Raise(expression, _) => self.lint_expression(expression),
+            _ => self.lint_expression(ast),
+        }
+    }
+
+    fn lint_block(&mut self, statements: &[ASTNode]) {
+        for (index, statement) in statements.iter().enumerate() {
+            self.lint_statement(statement);
+
+            let keyword = match statement {
+                ASTNode::Return(..) => "r",
+                ASTNode::Raise(..) => "e",
+                statement if exits(statement) => "exit",
+                _ => continue,
+            };
+            if let Some(unreachable) = statements.get(index + 1) {
+                self.diagnostics.push(
+                    Diagnostic::warning(
+                        format!("Code after {} is never executed", keyword),
+                        statement_span(unreachable),
+                    )
+                    .with_code(UNREACHABLE_CODE),
+                );
+                // the remaining statements still count as uses of names
+                for statement in &statements[index + 1..] {
+                    self.lint_statement(statement);
+                }
+            }
+            return;
+        }
+    }
+
+    fn check_naming(&mut self, kind: &str, name: &str, span: Span) {
+        if !is_lower_snake_case(name) {
+            self.diagnostics.push(
+                Diagnostic::warning(
+                    format!("{} {} should be written in lower snake case", kind, name),
+                    span,
+                )
+                .with_code(NAMING),
+            );
+        }
+    }
+
+    // expressions
+
+    fn lint_expression(&mut self, ast: &ASTNode) {
+        match ast {
+            ASTNode::Identifier(name, _) => {
+                self.read.insert(name.clone());
+            }
+            ASTNode::FunctionCall(name, _) => {
+                self.called.insert(name.clone());
+                self.read.insert(name.clone());
+            }
+            ASTNode::Call(callee, arguments, _) => {
+                if let ASTNode::Identifier(name, _) = callee.as_ref() {
+                    self.called.insert(name.clone());
+                }
+                self.lint_expression(callee);
+                for argument in arguments {
+                    self.lint_expression(argument);
+                }
+            }
+            ASTNode::List(elements, _) => {
+                for element in elements {
+                    self.lint_expression(element);
+                }
+            }
+            ASTNode::Lambda(_, body, _) => self.lint_block(body),
+            ASTNode::Binary(left, _, right, _) => {
+                self.lint_expression(left);
+                self.lint_expression(right);
+            }
+            ASTNode::Unary(_, right, _) => self.lint_expression(right),
+            _ => {}
+        }
+    }
+}
+
+// whether a loop body contains an r, e or exit call that leaves the loop; an e inside a t
+// body is caught by its handler, so it only leaves when `caught` is false
+fn returns(statements: &[ASTNode], caught: bool) -> bool {
+    statements.iter().any(|statement| match statement {
+        ASTNode::Return(..) => true,
+        statement if exits(statement) => true,
+        ASTNode::Raise(..) => !caught,
+        ASTNode::If(_, statements, _) | ASTNode::While(_, statements, _) => {
+            returns(statements, caught)
+        }
+        ASTNode::Try(statements, _, handler, _) => {
+            returns(statements, true) || returns(handler, caught)
+        }
+        _ => false,
+    })
+}
+
+// a call to the exit built-in, which ends the script even inside t
+fn exits(statement: &ASTNode) -> bool {
+    matches!(statement, ASTNode::Call(callee, _, _)
+        if matches!(callee.as_ref(), ASTNode::Identifier(name, _) if name == "exit"))
+}
+
+fn is_lower_snake_case(name: &str) -> bool {
+    !name.chars().any(char::is_uppercase)
+}
+
+fn statement_span(ast: &ASTNode) -> Span {
+    match ast {
+        ASTNode::VariableDeclaration(_, _, _, span)
+        | ASTNode::VariableAssignment(_, _, span)
+        | ASTNode::CompoundAssignment(_, _, _, span)
+        | ASTNode::FunctionCall(_, span)
+        | ASTNode::FunctionDeclaration(_, _, _, span)
+        | ASTNode::Call(_, _, span)
+        | ASTNode::Print(_, span)
+        | ASTNode::Return(_, span)
+        | ASTNode::If(_, _, span)
+        | ASTNode::While(_, _, span)
+        | ASTNode::Try(_, _, _, span)
+        | ASTNode::Raise(_, span) => *span,
+        _ => Span::default(),
+    }
+}
+
+// collects `// lint:allow(rule, ...)` comments by line; they apply to their own line and,
+// when written on a line of their own, to the line below
+fn suppressions(tokens: &[(Token, Span)]) -> HashMap<usize, Vec<String>> {
+    let mut suppressed: HashMap<usize, Vec<String>> = HashMap::new();
+    let mut code_lines = HashSet::new();
+
+    for (token, span) in tokens {
+        match token {
+            Token::Comment(comment) => {
+                let rules = match comment.trim().strip_prefix(SUPPRESSION_PREFIX) {
+                    Some(rules) => rules.trim_end_matches(')'),
+                    None => continue,
+                };
+                let rules: Vec<String> = rules
+                    .split(',')
+                    .map(|rule| rule.trim().to_string())
+                    .collect();
+
+                let line = if code_lines.contains(&span.line) {
+                    span.line
+                } else {
+                    span.line + 1
+                };
+                suppressed.entry(line).or_default().extend(rules);
+            }
+            _ => {
+                code_lines.insert(span.line);
+            }
+        }
+    }
+
+    suppressed
+}
+
+pub fn lint(code: &str) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    let tokens = tokenize(code).map_err(|error| vec![error.into()])?;
+    let suppressed = suppressions(&tokens);
+    let ast = parse(tokens)?;
+
+    let linter = Linter::new();
+    let diagnostics = linter
+        .lint(&ast)
+        .into_iter()
+        .filter(|diagnostic| {
+            let rules = match suppressed.get(&diagnostic.span.line) {
+                Some(rules) => rules,
+                None => return true,
+            };
+            !rules
+                .iter()
+                .any(|rule| rule == "all" || Some(rule.as_str()) == diagnostic.code)
+        })
+        .collect();
+
+    Ok(diagnostics)
+}
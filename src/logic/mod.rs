@@ -1,3 +1,16 @@
 pub mod tokenizer;
 pub mod ast;
-pub mod interpeter;
\ No newline at end of file
+pub mod diagnostic;
+pub mod error;
+pub mod resolver;
+pub mod typechecker;
+pub mod linter;
+pub mod optimizer;
+pub mod formatter;
+pub mod interpeter;
+pub mod environment;
+pub mod value;
+pub mod stdlib;
+pub mod limits;
+pub mod capabilities;
+pub mod tester;
\ No newline at end of file
@@ -0,0 +1,231 @@
+use std::rc::Rc;
+
+use super::{ast::ASTNode, tokenizer::Token};
+
+struct Optimizer;
+
+impl Optimizer {
+    fn optimize(&self, ast: ASTNode) -> ASTNode {
+        match ast {
+            ASTNode::Program(statements) => ASTNode::Program(self.optimize_block(statements)),
+            other => self
+                .optimize_statement(other)
+                .unwrap_or_else(|| ASTNode::Program(Vec::new())),
+        }
+    }
+
+    // statements
+
+    fn optimize_block(&self, statements: Vec<ASTNode>) -> Vec<ASTNode> {
+        let mut optimized = Vec::with_capacity(statements.len());
+
+        for statement in statements {
+            match self.optimize_statement(statement) {
+                Some(ASTNode::Program(nested)) => optimized.extend(nested),
+                Some(statement) => optimized.push(statement),
+                None => {}
+            }
+        }
+
+        optimized
+    }
+
+    fn optimize_body(&self, body: Rc<Vec<ASTNode>>) -> Rc<Vec<ASTNode>> {
+        let body = Rc::try_unwrap(body).unwrap_or_else(|body| (*body).clone());
+        Rc::new(self.optimize_block(body))
+    }
+
+    fn optimize_statement(&self, ast: ASTNode) -> Option<ASTNode> {
+        match ast {
+            ASTNode::Program(statements) => Some(ASTNode::Program(self.optimize_block(statements))),
+            ASTNode::VariableDeclaration(name, annotation, value, span) => {
+                Some(ASTNode::VariableDeclaration(
+                    name,
+                    annotation,
+                    Box::new(self.fold_expression(*value)),
+                    span,
+                ))
+            }
+            ASTNode::VariableAssignment(name, value, span) => Some(ASTNode::VariableAssignment(
+                name,
+                Box::new(self.fold_expression(*value)),
+                span,
+            )),
+            ASTNode::CompoundAssignment(name, operator, value, span) => {
+                Some(ASTNode::CompoundAssignment(
+                    name,
+                    operator,
+                    Box::new(self.fold_expression(*value)),
+                    span,
+                ))
+            }
+            ASTNode::FunctionDeclaration(name, parameters, body, span) => Some(
+                ASTNode::FunctionDeclaration(name, parameters, self.optimize_body(body), span),
+            ),
+            call @ ASTNode::Call(..) => Some(self.fold_expression(call)),
+            ASTNode::Print(expression, span) => Some(ASTNode::Print(
+                Box::new(self.fold_expression(*expression)),
+                span,
+            )),
+            ASTNode::Return(Some(expression), span) => Some(ASTNode::Return(
+                Some(Box::new(self.fold_expression(*expression))),
+                span,
+            )),
+            ASTNode::If(condition, statements, span) => match self.fold_expression(*condition) {
+                ASTNode::Boolean(false) => None,
+                // blocks don't introduce a scope, so a body that always runs can be inlined
+                ASTNode::Boolean(true) => Some(ASTNode::Program(self.optimize_block(statements))),
+                condition => Some(ASTNode::If(
+                    Box::new(condition),
+                    self.optimize_block(statements),
+                    span,
+                )),
+            },
+            ASTNode::While(condition, statements, span) => match self.fold_expression(*condition) {
+                ASTNode::Boolean(false) => None,
+                condition => Some(ASTNode::While(
+                    Box::new(condition),
+                    self.optimize_block(statements),
+                    span,
+                )),
+            },
+            ASTNode::Try(statements, name, handler, span) => {
+                let statements = self.optimize_block(statements);
+                // nothing can fail in an empty body, so the handler never runs
+                if statements.is_empty() {
+                    return None;
+                }
+                Some(ASTNode::Try(
+                    statements,
+                    name,
+                    self.optimize_block(handler),
+                    span,
+                ))
+            }
+            ASTNode::Raise(expression, span) => Some(ASTNode::Raise(
+                Box::new(self.fold_expression(*expression)),
+                span,
+            )),
+            other => Some(other),
+        }
+    }
+
+    // expressions
+
+    fn fold_expression(&self, ast: ASTNode) -> ASTNode {
+        match ast {
+            ASTNode::Binary(left, operator, right, span) => {
+                let left = self.fold_expression(*left);
+                let right = self.fold_expression(*right);
+
+                match self.fold_binary(&left, &operator, &right) {
+                    Some(folded) => folded,
+                    None => ASTNode::Binary(Box::new(left), operator, Box::new(right), span),
+                }
+            }
+            ASTNode::Unary(operator, right, span) => {
+                let right = self.fold_expression(*right);
+
+                match self.fold_unary(&operator, &right) {
+                    Some(folded) => folded,
+                    None => ASTNode::Unary(operator, Box::new(right), span),
+                }
+            }
+            ASTNode::List(elements, span) => ASTNode::List(
+                elements
+                    .into_iter()
+                    .map(|element| self.fold_expression(element))
+                    .collect(),
+                span,
+            ),
+            ASTNode::Lambda(parameters, body, span) => {
+                ASTNode::Lambda(parameters, self.optimize_body(body), span)
+            }
+            ASTNode::Call(callee, arguments, span) => ASTNode::Call(
+                Box::new(self.fold_expression(*callee)),
+                arguments
+                    .into_iter()
+                    .map(|argument| self.fold_expression(argument))
+                    .collect(),
+                span,
+            ),
+            other => other,
+        }
+    }
+
+    // folding only succeeds where the interpreter would produce a value; anything that
+    // would fail at runtime (type mismatches, overflow, division by zero) is left alone
+    fn fold_binary(&self, left: &ASTNode, operator: &Token, right: &ASTNode) -> Option<ASTNode> {
+        // a literal on the left already decides whether the default of ?? is used
+        if *operator == Token::QuestionQuestion {
+            return match left {
+                ASTNode::Nil => Some(right.clone()),
+                ASTNode::Number(_) | ASTNode::String(_) | ASTNode::Boolean(_) => Some(left.clone()),
+                _ => None,
+            };
+        }
+
+        match (left, right) {
+            (ASTNode::Number(left), ASTNode::Number(right)) => {
+                let (left, right) = (*left, *right);
+                match operator {
+                    Token::Plus => left.checked_add(right).map(ASTNode::Number),
+                    Token::Minus => left.checked_sub(right).map(ASTNode::Number),
+                    Token::Asterisk => left.checked_mul(right).map(ASTNode::Number),
+                    Token::Slash => left.checked_div(right).map(ASTNode::Number),
+                    Token::Percent => left.checked_rem(right).map(ASTNode::Number),
+                    Token::Pow => u32::try_from(right)
+                        .ok()
+                        .and_then(|right| left.checked_pow(right))
+                        .map(ASTNode::Number),
+                    Token::EqualEqual => Some(ASTNode::Boolean(left == right)),
+                    Token::Bang | Token::NotEqual => Some(ASTNode::Boolean(left != right)),
+                    Token::LessThan => Some(ASTNode::Boolean(left < right)),
+                    Token::LessThanEquals => Some(ASTNode::Boolean(left <= right)),
+                    Token::GreaterThan => Some(ASTNode::Boolean(left > right)),
+                    Token::GreaterThanEquals => Some(ASTNode::Boolean(left >= right)),
+                    Token::BitAnd => Some(ASTNode::Number(left & right)),
+                    Token::BitOr => Some(ASTNode::Number(left | right)),
+                    Token::Tilde => Some(ASTNode::Number(left ^ right)),
+                    Token::ShiftLeft => u32::try_from(right)
+                        .ok()
+                        .and_then(|right| left.checked_shl(right))
+                        .map(ASTNode::Number),
+                    Token::ShiftRight => u32::try_from(right)
+                        .ok()
+                        .and_then(|right| left.checked_shr(right))
+                        .map(ASTNode::Number),
+                    _ => None,
+                }
+            }
+            (ASTNode::String(left), ASTNode::String(right)) => match operator {
+                Token::Plus => Some(ASTNode::String(format!("{}{}", left, right))),
+                Token::EqualEqual => Some(ASTNode::Boolean(left == right)),
+                Token::Bang | Token::NotEqual => Some(ASTNode::Boolean(left != right)),
+                _ => None,
+            },
+            (ASTNode::Boolean(left), ASTNode::Boolean(right)) => match operator {
+                Token::EqualEqual => Some(ASTNode::Boolean(left == right)),
+                Token::Bang | Token::NotEqual => Some(ASTNode::Boolean(left != right)),
+                Token::And => Some(ASTNode::Boolean(*left && *right)),
+                Token::Or => Some(ASTNode::Boolean(*left || *right)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn fold_unary(&self, operator: &Token, right: &ASTNode) -> Option<ASTNode> {
+        match (operator, right) {
+            (Token::Minus, ASTNode::Number(right)) => right.checked_neg().map(ASTNode::Number),
+            (Token::Bang, ASTNode::Boolean(right)) => Some(ASTNode::Boolean(!right)),
+            (Token::Tilde, ASTNode::Number(right)) => Some(ASTNode::Number(!right)),
+            _ => None,
+        }
+    }
+}
+
+pub fn optimize(ast: ASTNode) -> ASTNode {
+    let optimizer = Optimizer;
+    optimizer.optimize(ast)
+}
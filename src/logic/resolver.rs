@@ -0,0 +1,376 @@
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use super::{ast::ASTNode, diagnostic::Diagnostic, stdlib::NATIVES, tokenizer::Span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Symbol {
+    Variable,
+    Function,
+}
+
+// Names in NETL2 are bound at runtime, so a function body sees whatever was declared
+// before it is called. The resolver mirrors that: it walks the program in execution order
+// and checks a function body at each call site against the names visible there. Bodies of
+// functions that are never called by name, lambdas included, are checked against
+// everything declared by the end of the enclosing function or file. Names declared inside
+// a body are local to it.
+struct Resolver<'a> {
+    symbols: HashMap<String, Symbol>,
+    declared_anywhere: HashSet<String>,
+    functions: Vec<Body<'a>>,
+    // most recently declared body for each function name
+    bodies: HashMap<String, Body<'a>>,
+    // bodies not called by name yet, checked at the end of the enclosing body or file
+    pending: Vec<Body<'a>>,
+    // generation of `symbols` each function body was last checked against
+    checked: HashMap<*const Vec<ASTNode>, usize>,
+    active: HashSet<*const Vec<ASTNode>>,
+    generation: usize,
+    // names declared in each loop being resolved, which earlier iterations may have run
+    loops: Vec<HashSet<String>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Clone, Copy)]
+struct Body<'a> {
+    parameters: &'a [String],
+    statements: &'a Rc<Vec<ASTNode>>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new() -> Self {
+        let mut symbols = HashMap::new();
+        for native in NATIVES {
+            symbols.insert(native.name.to_string(), Symbol::Function);
+        }
+
+        Self {
+            symbols,
+            declared_anywhere: HashSet::new(),
+            functions: Vec::new(),
+            bodies: HashMap::new(),
+            pending: Vec::new(),
+            checked: HashMap::new(),
+            active: HashSet::new(),
+            generation: 0,
+            loops: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn resolve(mut self, ast: &'a ASTNode) -> Vec<Diagnostic> {
+        self.collect_declarations(ast);
+        self.resolve_statement(ast);
+        self.resolve_pending(0);
+
+        let mut index = 0;
+        while index < self.functions.len() {
+            let body = self.functions[index];
+            if !self.checked.contains_key(&Rc::as_ptr(body.statements)) {
+                self.resolve_function(body);
+            }
+            index += 1;
+        }
+
+        self.diagnostics.sort_by_key(|diagnostic| diagnostic.span);
+        self.diagnostics
+    }
+
+    fn collect_declarations(&mut self, ast: &ASTNode) {
+        match ast {
+            ASTNode::Program(statements)
+            | ASTNode::If(_, statements, _)
+            | ASTNode::While(_, statements, _) => {
+                for statement in statements {
+                    self.collect_declarations(statement);
+                }
+            }
+            ASTNode::VariableDeclaration(name, _, value, _) => {
+                self.declared_anywhere.insert(name.clone());
+                self.collect_declarations(value);
+            }
+            ASTNode::Try(statements, name, handler, _) => {
+                self.declared_anywhere.insert(name.clone());
+                for statement in statements.iter().chain(handler) {
+                    self.collect_declarations(statement);
+                }
+            }
+            ASTNode::FunctionDeclaration(name, parameters, body, _) => {
+                self.declared_anywhere.insert(name.clone());
+                self.declared_anywhere.extend(parameters.iter().cloned());
+                for statement in body.iter() {
+                    self.collect_declarations(statement);
+                }
+            }
+            ASTNode::Lambda(parameters, body, _) => {
+                self.declared_anywhere.extend(parameters.iter().cloned());
+                for statement in body.iter() {
+                    self.collect_declarations(statement);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // statements
+
+    fn resolve_statement(&mut self, ast: &'a ASTNode) {
+        match ast {
+            ASTNode::Program(statements) => self.resolve_block(statements),
+            ASTNode::VariableDeclaration(name, _, value, span) => {
+                self.resolve_expression(value);
+
+                if self.symbols.get(name) == Some(&Symbol::Function) {
+                    self.report(Diagnostic::warning(
+                        format!("Variable {} shadows the function of the same name", name),
+                        *span,
+                    ));
+                }
+                self.declare(name, Symbol::Variable);
+            }
+            ASTNode::VariableAssignment(name, value, span) => {
+                self.resolve_expression(value);
+
+                match self.symbols.get(name) {
+                    Some(Symbol::Variable) => {}
+                    Some(Symbol::Function) => {
+                        self.report(Diagnostic::warning(
+                            format!(
+                                "Assignment to {} shadows the function of the same name",
+                                name
+                            ),
+                            *span,
+                        ));
+                        self.declare(name, Symbol::Variable);
+                    }
+                    None => self.report_undeclared(
+                        format!("Cannot assign to undeclared variable {}", name),
+                        name,
+                        *span,
+                    ),
+                }
+            }
+            ASTNode::CompoundAssignment(name, _, value, span) => {
+                self.resolve_expression(value);
+
+                match self.symbols.get(name) {
+                    Some(Symbol::Variable) => {}
+                    Some(Symbol::Function) => self.report(Diagnostic::error(
+                        format!("{} is a function, not a variable", name),
+                        *span,
+                    )),
+                    None => self.report_undeclared(
+                        self.undeclared_message("Variable", name),
+                        name,
+                        *span,
+                    ),
+                }
+            }
+            ASTNode::FunctionDeclaration(name, parameters, body, _) => {
+                let body = Body {
+                    parameters,
+                    statements: body,
+                };
+                self.functions.push(body);
+                self.bodies.insert(name.clone(), body);
+                self.declare(name, Symbol::Function);
+            }
+            ASTNode::FunctionCall(name, span) => self.resolve_call(name, 0, *span),
+            ASTNode::Print(expression, _) => self.resolve_expression(expression),
+            ASTNode::Return(Some(expression), _) => self.resolve_expression(expression),
+            ASTNode::If(condition, statements, _) => {
+                self.resolve_expression(condition);
+                self.resolve_block(statements);
+            }
+            ASTNode::While(condition, statements, _) => self.resolve_loop(condition, statements),
+            ASTNode::Try(statements, name, handler, _) => {
+                self.resolve_block(statements);
+                self.declare(name, Symbol::Variable);
+                self.resolve_block(handler);
+            }
+            ASTNode::Raise(expression, _) => self.resolve_expression(expression),
+            _ => self.resolve_expression(ast),
+        }
+    }
+
+    fn resolve_block(&mut self, statements: &'a [ASTNode]) {
+        for statement in statements {
+            self.resolve_statement(statement);
+        }
+    }
+
+    // A later iteration sees what the earlier ones declared, so a name declared anywhere
+    // in the body may already exist when it is used. The body is still resolved once;
+    // using such a name before its declaration depends on the iteration and is a warning.
+    fn resolve_loop(&mut self, condition: &'a ASTNode, statements: &'a [ASTNode]) {
+        let mut declared = HashSet::new();
+        for statement in statements {
+            loop_declarations(statement, &mut declared);
+        }
+
+        self.loops.push(declared);
+        self.resolve_expression(condition);
+        self.resolve_block(statements);
+        self.loops.pop();
+    }
+
+    fn resolve_call(&mut self, name: &str, arguments: usize, span: Span) {
+        match self.symbols.get(name) {
+            Some(Symbol::Function) => {
+                let body = self.bodies.get(name).copied();
+                let expected = match body {
+                    Some(body) => body.parameters.len(),
+                    None => match NATIVES.iter().find(|native| native.name == name) {
+                        Some(native) => native.arity,
+                        None => return,
+                    },
+                };
+
+                if expected != arguments {
+                    self.report(Diagnostic::error(
+                        format!(
+                            "Function {} expects {} arguments but got {}",
+                            name, expected, arguments
+                        ),
+                        span,
+                    ));
+                }
+                if let Some(body) = body {
+                    self.resolve_function(body);
+                }
+            }
+            // a variable can hold a function, which is checked at runtime
+            Some(Symbol::Variable) => {}
+            None => self.report_undeclared(self.undeclared_message("Function", name), name, span),
+        }
+    }
+
+    fn resolve_function(&mut self, body: Body<'a>) {
+        let key = Rc::as_ptr(body.statements);
+
+        // recursive calls and bodies already checked against the same names add nothing new
+        if self.active.contains(&key) || self.checked.get(&key) == Some(&self.generation) {
+            return;
+        }
+
+        let outer = self.symbols.clone();
+        let pending = self.pending.len();
+
+        self.active.insert(key);
+        for parameter in body.parameters {
+            self.declare(parameter, Symbol::Variable);
+        }
+        self.resolve_block(body.statements);
+        self.resolve_pending(pending);
+        self.active.remove(&key);
+
+        if self.symbols != outer {
+            self.symbols = outer;
+            self.generation += 1;
+        }
+        self.checked.insert(key, self.generation);
+    }
+
+    fn resolve_pending(&mut self, from: usize) {
+        while self.pending.len() > from {
+            let body = self.pending.remove(from);
+            self.resolve_function(body);
+        }
+    }
+
+    // expressions
+
+    fn resolve_expression(&mut self, ast: &'a ASTNode) {
+        match ast {
+            ASTNode::Binary(left, _, right, _) => {
+                self.resolve_expression(left);
+                self.resolve_expression(right);
+            }
+            ASTNode::Unary(_, right, _) => self.resolve_expression(right),
+            ASTNode::Identifier(name, span) if !self.symbols.contains_key(name) => {
+                self.report_undeclared(self.undeclared_message("Variable", name), name, *span);
+            }
+            ASTNode::List(elements, _) => {
+                for element in elements {
+                    self.resolve_expression(element);
+                }
+            }
+            ASTNode::Lambda(parameters, body, _) => self.pending.push(Body {
+                parameters,
+                statements: body,
+            }),
+            ASTNode::Call(callee, arguments, span) => {
+                for argument in arguments {
+                    self.resolve_expression(argument);
+                }
+                match callee.as_ref() {
+                    ASTNode::Identifier(name, _) => self.resolve_call(name, arguments.len(), *span),
+                    callee => self.resolve_expression(callee),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // helpers
+
+    fn declare(&mut self, name: &str, symbol: Symbol) {
+        if self.symbols.insert(name.to_string(), symbol) != Some(symbol) {
+            self.generation += 1;
+        }
+    }
+
+    fn report_undeclared(&mut self, message: String, name: &str, span: Span) {
+        if self.loops.iter().any(|declared| declared.contains(name)) {
+            self.report(Diagnostic::warning(
+                format!("{} on the first iteration of the loop", message),
+                span,
+            ));
+        } else {
+            self.report(Diagnostic::error(message, span));
+        }
+    }
+
+    fn undeclared_message(&self, kind: &str, name: &str) -> String {
+        if self.declared_anywhere.contains(name) {
+            format!("{} {} is used before it is declared", kind, name)
+        } else {
+            format!("{} {} is not declared", kind, name)
+        }
+    }
+
+    fn report(&mut self, diagnostic: Diagnostic) {
+        if !self.diagnostics.contains(&diagnostic) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+}
+
+// names a loop body declares for the iterations after it, not counting function bodies
+fn loop_declarations(ast: &ASTNode, declared: &mut HashSet<String>) {
+    match ast {
+        ASTNode::VariableDeclaration(name, ..) | ASTNode::FunctionDeclaration(name, ..) => {
+            declared.insert(name.clone());
+        }
+        ASTNode::If(_, statements, _) | ASTNode::While(_, statements, _) => {
+            for statement in statements {
+                loop_declarations(statement, declared);
+            }
+        }
+        ASTNode::Try(statements, name, handler, _) => {
+            declared.insert(name.clone());
+            for statement in statements.iter().chain(handler) {
+                loop_declarations(statement, declared);
+            }
+        }
+        _ => {}
+    }
+}
+
+pub fn resolve(ast: &ASTNode) -> Vec<Diagnostic> {
+    let resolver = Resolver::new();
+    resolver.resolve(ast)
+}
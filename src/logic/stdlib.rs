@@ -0,0 +1,710 @@
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::Path,
+    rc::Rc,
+};
+
+use super::{
+    capabilities::Capability,
+    error::RuntimeError,
+    interpeter::{list_size, Interpreter},
+    tokenizer::Span,
+    value::Value,
+};
+
+// a function implemented in Rust, available to every script under its name
+pub struct Native {
+    pub name: &'static str,
+    pub arity: usize,
+    pub function: fn(&mut Interpreter, Vec<Value>, Span) -> Result<Value, RuntimeError>,
+    // what the interpreter must be allowed to do before the function can be called
+    pub capability: Option<Capability>,
+    // whether a failure should report the source of the checked arguments
+    pub assertion: bool,
+}
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Native({})", self.name)
+    }
+}
+
+pub static NATIVES: &[Native] = &[
+    Native {
+        name: "len",
+        arity: 1,
+        function: len,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "str",
+        arity: 1,
+        function: str,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "int",
+        arity: 1,
+        function: int,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "range",
+        arity: 1,
+        function: range,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "get",
+        arity: 2,
+        function: get,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "map",
+        arity: 2,
+        function: map,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "filter",
+        arity: 2,
+        function: filter,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "fold",
+        arity: 3,
+        function: fold,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "input",
+        arity: 0,
+        function: input,
+        capability: Some(Capability::Stdin),
+        assertion: false,
+    },
+    Native {
+        name: "clock",
+        arity: 0,
+        function: clock,
+        capability: Some(Capability::Clock),
+        assertion: false,
+    },
+    Native {
+        name: "random",
+        arity: 1,
+        function: random,
+        capability: Some(Capability::Random),
+        assertion: false,
+    },
+    Native {
+        name: "args",
+        arity: 0,
+        function: args,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "env",
+        arity: 1,
+        function: env,
+        capability: Some(Capability::Env),
+        assertion: false,
+    },
+    Native {
+        name: "set_exit_code",
+        arity: 1,
+        function: set_exit_code,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "exit",
+        arity: 1,
+        function: exit,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "assert",
+        arity: 1,
+        function: assert,
+        capability: None,
+        assertion: true,
+    },
+    Native {
+        name: "assert_eq",
+        arity: 2,
+        function: assert_eq,
+        capability: None,
+        assertion: true,
+    },
+    Native {
+        name: "error",
+        arity: 2,
+        function: error,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "is_error",
+        arity: 1,
+        function: is_error,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "error_kind",
+        arity: 1,
+        function: error_kind,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "error_message",
+        arity: 1,
+        function: error_message,
+        capability: None,
+        assertion: false,
+    },
+    Native {
+        name: "read_file",
+        arity: 1,
+        function: read_file,
+        capability: Some(Capability::FsRead),
+        assertion: false,
+    },
+    Native {
+        name: "read_lines",
+        arity: 1,
+        function: read_lines,
+        capability: Some(Capability::FsRead),
+        assertion: false,
+    },
+    Native {
+        name: "write_file",
+        arity: 2,
+        function: write_file,
+        capability: Some(Capability::FsWrite),
+        assertion: false,
+    },
+    Native {
+        name: "append_file",
+        arity: 2,
+        function: append_file,
+        capability: Some(Capability::FsWrite),
+        assertion: false,
+    },
+    Native {
+        name: "file_exists",
+        arity: 1,
+        function: file_exists,
+        capability: Some(Capability::FsRead),
+        assertion: false,
+    },
+    Native {
+        name: "list_dir",
+        arity: 1,
+        function: list_dir,
+        capability: Some(Capability::FsRead),
+        assertion: false,
+    },
+    Native {
+        name: "delete_file",
+        arity: 1,
+        function: delete_file,
+        capability: Some(Capability::FsWrite),
+        assertion: false,
+    },
+];
+
+fn len(_: &mut Interpreter, arguments: Vec<Value>, span: Span) -> Result<Value, RuntimeError> {
+    match &arguments[0] {
+        Value::String(value) => Ok(Value::Number(value.chars().count() as i32)),
+        Value::List(values) => Ok(Value::Number(values.len() as i32)),
+        value => Err(invalid_argument("len", "a str or list", value, span)),
+    }
+}
+
+fn str(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let string = interpreter.stringify_value(&arguments[0]);
+    interpreter.allocate(string.len(), span)?;
+    Ok(Value::String(string))
+}
+
+// converts a str of digits or a bool, failing on anything that is not a number
+fn int(_: &mut Interpreter, arguments: Vec<Value>, span: Span) -> Result<Value, RuntimeError> {
+    match &arguments[0] {
+        Value::Number(value) => Ok(Value::Number(*value)),
+        Value::Boolean(value) => Ok(Value::Number(*value as i32)),
+        Value::String(value) => match value.trim().parse() {
+            Ok(number) => Ok(Value::Number(number)),
+            Err(_) => Err(RuntimeError::InvalidValue(
+                format!("Cannot convert {:?} to int", value),
+                span,
+            )),
+        },
+        value => Err(invalid_argument("int", "a str, int or bool", value, span)),
+    }
+}
+
+fn range(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    match &arguments[0] {
+        Value::Number(count) => {
+            interpreter.allocate(list_size((*count).max(0) as usize), span)?;
+            Ok(Value::List(Rc::new(
+                (0..*count).map(Value::Number).collect(),
+            )))
+        }
+        value => Err(invalid_argument("range", "an int", value, span)),
+    }
+}
+
+fn get(_: &mut Interpreter, arguments: Vec<Value>, span: Span) -> Result<Value, RuntimeError> {
+    let values = list_argument("get", &arguments[0], span)?;
+    match &arguments[1] {
+        Value::Number(index) => {
+            match usize::try_from(*index)
+                .ok()
+                .and_then(|index| values.get(index))
+            {
+                Some(value) => Ok(value.clone()),
+                None => Err(RuntimeError::InvalidValue(
+                    format!(
+                        "Index {} is out of range for a list of length {}",
+                        index,
+                        values.len()
+                    ),
+                    span,
+                )),
+            }
+        }
+        value => Err(invalid_argument("get", "an int index", value, span)),
+    }
+}
+
+fn map(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let values = list_argument("map", &arguments[0], span)?;
+    interpreter.allocate(list_size(values.len()), span)?;
+    let mapped = values
+        .iter()
+        .map(|value| interpreter.call(&arguments[1], vec![value.clone()], span))
+        .collect::<Result<_, _>>()?;
+    Ok(Value::List(Rc::new(mapped)))
+}
+
+fn filter(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let values = list_argument("filter", &arguments[0], span)?;
+    let mut kept = Vec::new();
+    for value in values.iter() {
+        let keep = interpreter.call(&arguments[1], vec![value.clone()], span)?;
+        if interpreter.is_true(&keep) {
+            kept.push(value.clone());
+        }
+    }
+    interpreter.allocate(list_size(kept.len()), span)?;
+    Ok(Value::List(Rc::new(kept)))
+}
+
+fn fold(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let values = list_argument("fold", &arguments[0], span)?;
+    let mut accumulator = arguments[1].clone();
+    for value in values.iter() {
+        accumulator = interpreter.call(&arguments[2], vec![accumulator, value.clone()], span)?;
+    }
+    Ok(accumulator)
+}
+
+// reads one line, without its line break, or an empty string at the end of the input
+fn input(_: &mut Interpreter, _: Vec<Value>, span: Span) -> Result<Value, RuntimeError> {
+    let mut line = String::new();
+    if let Err(error) = std::io::stdin().read_line(&mut line) {
+        return Err(RuntimeError::InvalidValue(
+            format!("Could not read input: {}", error),
+            span,
+        ));
+    }
+
+    let length = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(length);
+    Ok(Value::String(line))
+}
+
+// milliseconds since the script started
+fn clock(interpreter: &mut Interpreter, _: Vec<Value>, _: Span) -> Result<Value, RuntimeError> {
+    let elapsed = interpreter.elapsed().as_millis();
+    Ok(Value::Number(i32::try_from(elapsed).unwrap_or(i32::MAX)))
+}
+
+// a number from 0 up to, but not including, the argument
+fn random(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    match &arguments[0] {
+        Value::Number(bound) if *bound > 0 => {
+            let random = interpreter.next_random() % *bound as u64;
+            Ok(Value::Number(random as i32))
+        }
+        Value::Number(bound) => Err(RuntimeError::InvalidValue(
+            format!("random expects a positive bound but got {}", bound),
+            span,
+        )),
+        value => Err(invalid_argument("random", "an int", value, span)),
+    }
+}
+
+// process
+
+fn args(interpreter: &mut Interpreter, _: Vec<Value>, _: Span) -> Result<Value, RuntimeError> {
+    let arguments = interpreter
+        .arguments()
+        .iter()
+        .map(|argument| Value::String(argument.clone()))
+        .collect();
+    Ok(Value::List(Rc::new(arguments)))
+}
+
+// value of an environment variable, or an error value if it is not set
+fn env(_: &mut Interpreter, arguments: Vec<Value>, span: Span) -> Result<Value, RuntimeError> {
+    let name = string_argument("env", &arguments[0], span)?;
+    match std::env::var(name) {
+        Ok(value) => Ok(Value::String(value)),
+        Err(std::env::VarError::NotPresent) => Ok(Value::error(
+            "not_found",
+            format!("Environment variable {} is not set", name),
+        )),
+        Err(std::env::VarError::NotUnicode(_)) => Ok(Value::error(
+            "invalid_data",
+            format!("Environment variable {} is not valid unicode", name),
+        )),
+    }
+}
+
+// the status the process exits with once the script has finished
+fn set_exit_code(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    match &arguments[0] {
+        Value::Number(code) => {
+            interpreter.set_exit_code(*code);
+            Ok(Value::Number(*code))
+        }
+        value => Err(invalid_argument("set_exit_code", "an int", value, span)),
+    }
+}
+
+// stops the script right away, unlike set_exit_code
+fn exit(_: &mut Interpreter, arguments: Vec<Value>, span: Span) -> Result<Value, RuntimeError> {
+    match &arguments[0] {
+        Value::Number(code) => Err(RuntimeError::Exit(*code, span)),
+        value => Err(invalid_argument("exit", "an int", value, span)),
+    }
+}
+
+// assertions, which stop the script when they fail
+
+fn assert(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    if interpreter.is_true(&arguments[0]) {
+        Ok(Value::Boolean(true))
+    } else {
+        Err(RuntimeError::AssertionFailed(None, None, span))
+    }
+}
+
+fn assert_eq(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    if arguments[0] == arguments[1] {
+        Ok(Value::Boolean(true))
+    } else {
+        let left = interpreter.describe_value(&arguments[0]);
+        let right = interpreter.describe_value(&arguments[1]);
+        Err(RuntimeError::AssertionFailed(
+            None,
+            Some((left, right)),
+            span,
+        ))
+    }
+}
+
+// errors
+
+// an error value with the given kind, to be returned or raised with e
+fn error(_: &mut Interpreter, arguments: Vec<Value>, span: Span) -> Result<Value, RuntimeError> {
+    let kind = string_argument("error", &arguments[0], span)?;
+    let message = string_argument("error", &arguments[1], span)?;
+    Ok(Value::error(kind, message.to_string()))
+}
+
+fn is_error(_: &mut Interpreter, arguments: Vec<Value>, _: Span) -> Result<Value, RuntimeError> {
+    Ok(Value::Boolean(matches!(arguments[0], Value::Error(_))))
+}
+
+fn error_kind(
+    _: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    match &arguments[0] {
+        Value::Error(error) => Ok(Value::String(error.kind.clone())),
+        value => Err(invalid_argument("error_kind", "an error", value, span)),
+    }
+}
+
+fn error_message(
+    _: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    match &arguments[0] {
+        Value::Error(error) => Ok(Value::String(error.message.clone())),
+        value => Err(invalid_argument("error_message", "an error", value, span)),
+    }
+}
+
+// files, which return an error value instead of stopping the script when the file system
+// fails, so scripts can handle a missing file themselves
+
+fn read_file(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let path = path_argument(
+        interpreter,
+        "read_file",
+        Capability::FsRead,
+        &arguments[0],
+        span,
+    )?;
+    match fs::read_to_string(path) {
+        Ok(contents) => {
+            interpreter.allocate(contents.len(), span)?;
+            Ok(Value::String(contents))
+        }
+        Err(error) => Ok(io_error(path, error)),
+    }
+}
+
+fn read_lines(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let path = path_argument(
+        interpreter,
+        "read_lines",
+        Capability::FsRead,
+        &arguments[0],
+        span,
+    )?;
+    match fs::read_to_string(path) {
+        Ok(contents) => {
+            let lines: Vec<Value> = contents
+                .lines()
+                .map(|line| Value::String(line.to_string()))
+                .collect();
+            interpreter.allocate(list_size(lines.len()).max(contents.len()), span)?;
+            Ok(Value::List(Rc::new(lines)))
+        }
+        Err(error) => Ok(io_error(path, error)),
+    }
+}
+
+fn write_file(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let path = path_argument(
+        interpreter,
+        "write_file",
+        Capability::FsWrite,
+        &arguments[0],
+        span,
+    )?;
+    let contents = string_argument("write_file", &arguments[1], span)?;
+    Ok(io_result(path, fs::write(path, contents)))
+}
+
+fn append_file(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let path = path_argument(
+        interpreter,
+        "append_file",
+        Capability::FsWrite,
+        &arguments[0],
+        span,
+    )?;
+    let contents = string_argument("append_file", &arguments[1], span)?;
+    let result = fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()));
+    Ok(io_result(path, result))
+}
+
+fn file_exists(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let path = path_argument(
+        interpreter,
+        "file_exists",
+        Capability::FsRead,
+        &arguments[0],
+        span,
+    )?;
+    Ok(Value::Boolean(Path::new(path).exists()))
+}
+
+// names of the entries of a directory, sorted
+fn list_dir(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let path = path_argument(
+        interpreter,
+        "list_dir",
+        Capability::FsRead,
+        &arguments[0],
+        span,
+    )?;
+    let entries = fs::read_dir(path).and_then(|entries| {
+        entries
+            .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().into_owned()))
+            .collect::<Result<Vec<String>, _>>()
+    });
+
+    match entries {
+        Ok(mut names) => {
+            names.sort();
+            interpreter.allocate(list_size(names.len()), span)?;
+            Ok(Value::List(Rc::new(
+                names.into_iter().map(Value::String).collect(),
+            )))
+        }
+        Err(error) => Ok(io_error(path, error)),
+    }
+}
+
+fn delete_file(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let path = path_argument(
+        interpreter,
+        "delete_file",
+        Capability::FsWrite,
+        &arguments[0],
+        span,
+    )?;
+    Ok(io_result(path, fs::remove_file(path)))
+}
+
+// helpers
+
+fn list_argument(name: &str, value: &Value, span: Span) -> Result<Rc<Vec<Value>>, RuntimeError> {
+    match value {
+        Value::List(values) => Ok(values.clone()),
+        value => Err(invalid_argument(name, "a list", value, span)),
+    }
+}
+
+fn string_argument<'a>(name: &str, value: &'a Value, span: Span) -> Result<&'a str, RuntimeError> {
+    match value {
+        Value::String(value) => Ok(value),
+        value => Err(invalid_argument(name, "a str", value, span)),
+    }
+}
+
+fn path_argument<'a>(
+    interpreter: &Interpreter,
+    name: &str,
+    capability: Capability,
+    value: &'a Value,
+    span: Span,
+) -> Result<&'a str, RuntimeError> {
+    let path = string_argument(name, value, span)?;
+    interpreter.require_path(capability, path, span)?;
+    Ok(path)
+}
+
+// true when the operation succeeded
+fn io_result(path: &str, result: io::Result<()>) -> Value {
+    match result {
+        Ok(()) => Value::Boolean(true),
+        Err(error) => io_error(path, error),
+    }
+}
+
+fn io_error(path: &str, error: io::Error) -> Value {
+    let kind = match error.kind() {
+        io::ErrorKind::NotFound => "not_found",
+        io::ErrorKind::PermissionDenied => "permission_denied",
+        io::ErrorKind::AlreadyExists => "already_exists",
+        io::ErrorKind::InvalidData => "invalid_data",
+        _ => "io",
+    };
+    Value::error(kind, format!("{}: {}", path, error))
+}
+
+fn invalid_argument(name: &str, expected: &str, value: &Value, span: Span) -> RuntimeError {
+    RuntimeError::InvalidValue(
+        format!(
+            "{} expects {} but got {}",
+            name,
+            expected,
+            value.type_name()
+        ),
+        span,
+    )
+}
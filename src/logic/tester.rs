@@ -0,0 +1,70 @@
+use super::{
+    ast::ASTNode, capabilities::Capabilities, error::RuntimeError, interpeter::Interpreter,
+    limits::Limits, tokenizer::Span,
+};
+
+pub const TEST_PREFIX: &str = "test_";
+
+pub struct TestResult {
+    pub name: String,
+    pub result: Result<(), RuntimeError>,
+}
+
+// tests are the functions declared at the top of a file whose name starts with test_ and
+// that take no parameters
+pub fn test_names(ast: &ASTNode) -> Vec<String> {
+    let statements = match ast {
+        ASTNode::Program(statements) => statements,
+        _ => return Vec::new(),
+    };
+
+    statements
+        .iter()
+        .filter_map(|statement| match statement {
+            ASTNode::FunctionDeclaration(name, parameters, _, _)
+                if name.starts_with(TEST_PREFIX) && parameters.is_empty() =>
+            {
+                Some(name.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// The top of the file runs once, under the limits and capabilities the script would run
+// with, so the tests see the same declarations as the script would. Every test then
+// starts from the globals as the top of the file left them, so what one test changes is
+// not seen by the others. A test passes when it returns without an error and without
+// exiting with a non-zero status.
+pub fn run_tests(ast: &ASTNode, limits: Limits, capabilities: &Capabilities) -> Vec<TestResult> {
+    let names = test_names(ast);
+    if names.is_empty() {
+        return Vec::new();
+    }
+
+    let mut interpreter = Interpreter::new()
+        .with_limits(limits)
+        .with_capabilities(capabilities.clone());
+    let setup = interpreter.run(ast).and_then(|_| exit_status(&interpreter));
+    let snapshot = interpreter.snapshot();
+
+    names
+        .into_iter()
+        .map(|name| {
+            let result = setup.clone().and_then(|_| {
+                interpreter.restore(&snapshot);
+                interpreter
+                    .run_function(&name)
+                    .and_then(|_| exit_status(&interpreter))
+            });
+            TestResult { name, result }
+        })
+        .collect()
+}
+
+fn exit_status(interpreter: &Interpreter) -> Result<(), RuntimeError> {
+    match interpreter.exit_code() {
+        0 => Ok(()),
+        code => Err(RuntimeError::Exit(code, Span::default())),
+    }
+}
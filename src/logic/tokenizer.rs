@@ -1,8 +1,13 @@
+use unicode_xid::UnicodeXID;
+
+use super::diagnostic::Diagnostic;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Number(i32),
     String(String),
     Boolean(bool),
+    Nil,
     Identifier(String),
     V,
     W,
@@ -11,6 +16,7 @@ pub enum Token {
     Print,
     Equals,
     Comma,
+    Colon,
     EqualEqual,
     Bang,
     NotEqual,
@@ -20,6 +26,18 @@ pub enum Token {
     GreaterThanEquals,
     And,
     Or,
+    BitAnd,
+    BitOr,
+    Tilde,
+    QuestionQuestion,
+    ShiftLeft,
+    ShiftRight,
+    PlusEquals,
+    MinusEquals,
+    AsteriskEquals,
+    SlashEquals,
+    PercentEquals,
+    PowEquals,
     Plus,
     Minus,
     Asterisk,
@@ -30,134 +48,357 @@ pub enum Token {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Return,
+    Try,
+    Catch,
+    Raise,
+    Comment(String),
     EndOfFile,
-    Unknown,
 }
 
-pub fn tokenize(code: &str) -> Vec<Token> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexError {
+    pub character: char,
+    pub span: Span,
+    // how to fix a common mistake, if the character looks like one
+    pub suggestion: Option<&'static str>,
+}
+
+impl LexError {
+    fn new(character: char, span: Span) -> Self {
+        Self {
+            character,
+            span,
+            suggestion: suggestion(character),
+        }
+    }
+
+    pub fn message(&self) -> String {
+        format!("Unexpected character {:?}", self.character)
+    }
+}
+
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Error: {} at {}", self.message(), self.span)?;
+        if let Some(suggestion) = self.suggestion {
+            write!(f, "\n  help: {}", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(error: LexError) -> Self {
+        let message = match error.suggestion {
+            Some(suggestion) => format!("{} ({})", error.message(), suggestion),
+            None => error.message(),
+        };
+        Diagnostic::error(message, error.span)
+    }
+}
+
+// a single & or | is a bitwise operator, so their hints are shown by the typechecker
+// and the interpreter when one is applied to bools
+pub fn suggestion(character: char) -> Option<&'static str> {
+    match character {
+        ';' => Some("statements end at the end of the line, remove the ;"),
+        '#' => Some("comments start with //"),
+        '\'' => Some("strings use double quotes"),
+        '.' => Some("numbers have no fractional part"),
+        '&' => Some("use && for a logical and"),
+        '|' => Some("use || for a logical or"),
+        _ => None,
+    }
+}
+
+pub fn tokenize(code: &str) -> Result<Vec<(Token, Span)>, LexError> {
     let mut tokens = Vec::new();
-    let mut iter = code.chars().peekable();
+    let mut iter = code
+        .chars()
+        .scan(Span::new(1, 1), |position, ch| {
+            let span = *position;
+            if ch == '\n' {
+                *position = Span::new(position.line + 1, 1);
+            } else {
+                position.column += 1;
+            }
+            Some((ch, span))
+        })
+        .peekable();
+    let mut end = Span::new(1, 1);
 
-    while let Some(ch) = iter.next() {
+    while let Some((ch, span)) = iter.next() {
+        end = span;
         match ch {
             ' ' | '\t' | '\n' | '\r' => continue,
             '!' => {
-                if let Some(&ch) = iter.peek() {
+                if let Some(&(ch, _)) = iter.peek() {
                     match ch {
                         '=' => {
                             iter.next();
-                            tokens.push(Token::NotEqual);
+                            tokens.push((Token::NotEqual, span));
                         }
-                        _ => tokens.push(Token::Bang),
+                        _ => tokens.push((Token::Bang, span)),
                     }
                 } else {
-                    tokens.push(Token::Bang);
+                    tokens.push((Token::Bang, span));
                 }
             }
             '=' => {
-                if let Some(&ch) = iter.peek() {
+                if let Some(&(ch, _)) = iter.peek() {
                     match ch {
                         '=' => {
                             iter.next();
-                            tokens.push(Token::EqualEqual);
+                            tokens.push((Token::EqualEqual, span));
                         }
-                        _ => tokens.push(Token::Equals),
+                        _ => tokens.push((Token::Equals, span)),
                     }
                 } else {
-                    tokens.push(Token::Equals);
+                    tokens.push((Token::Equals, span));
                 }
             }
-            ',' => tokens.push(Token::Comma),
+            ',' => tokens.push((Token::Comma, span)),
+            ':' => tokens.push((Token::Colon, span)),
             '<' => {
-                if let Some(&ch) = iter.peek() {
+                if let Some(&(ch, _)) = iter.peek() {
                     match ch {
                         '=' => {
                             iter.next();
-                            tokens.push(Token::LessThanEquals);
+                            tokens.push((Token::LessThanEquals, span));
                         }
-                        _ => tokens.push(Token::LessThan),
+                        '<' => {
+                            iter.next();
+                            tokens.push((Token::ShiftLeft, span));
+                        }
+                        _ => tokens.push((Token::LessThan, span)),
                     }
                 } else {
-                    tokens.push(Token::LessThan);
+                    tokens.push((Token::LessThan, span));
                 }
             }
             '>' => {
-                if let Some(&ch) = iter.peek() {
+                if let Some(&(ch, _)) = iter.peek() {
                     match ch {
                         '=' => {
                             iter.next();
-                            tokens.push(Token::GreaterThanEquals);
+                            tokens.push((Token::GreaterThanEquals, span));
                         }
-                        _ => tokens.push(Token::GreaterThan),
+                        '>' => {
+                            iter.next();
+                            tokens.push((Token::ShiftRight, span));
+                        }
+                        _ => tokens.push((Token::GreaterThan, span)),
                     }
                 } else {
-                    tokens.push(Token::GreaterThan);
-                }
-            }
-            '&' => tokens.push(Token::And),
-            '|' => tokens.push(Token::Or),
-            '+' => tokens.push(Token::Plus),
-            '-' => tokens.push(Token::Minus),
-            '*' => tokens.push(Token::Asterisk),
-            '/' => tokens.push(Token::Slash),
-            '%' => tokens.push(Token::Percent),
-            '^' => tokens.push(Token::Pow),
-            '(' => tokens.push(Token::LeftParen),
-            ')' => tokens.push(Token::RightParen),
-            '{' => tokens.push(Token::LeftBrace),
-            '}' => tokens.push(Token::RightBrace),
+                    tokens.push((Token::GreaterThan, span));
+                }
+            }
+            '&' => {
+                if let Some(&('&', _)) = iter.peek() {
+                    iter.next();
+                    tokens.push((Token::And, span));
+                } else {
+                    tokens.push((Token::BitAnd, span));
+                }
+            }
+            '|' => {
+                if let Some(&('|', _)) = iter.peek() {
+                    iter.next();
+                    tokens.push((Token::Or, span));
+                } else {
+                    tokens.push((Token::BitOr, span));
+                }
+            }
+            '~' => tokens.push((Token::Tilde, span)),
+            '?' => {
+                if let Some(&('?', _)) = iter.peek() {
+                    iter.next();
+                    tokens.push((Token::QuestionQuestion, span));
+                } else {
+                    return Err(LexError::new(ch, span));
+                }
+            }
+            '+' => {
+                if let Some(&('=', _)) = iter.peek() {
+                    iter.next();
+                    tokens.push((Token::PlusEquals, span));
+                } else {
+                    tokens.push((Token::Plus, span));
+                }
+            }
+            '-' => {
+                if let Some(&('=', _)) = iter.peek() {
+                    iter.next();
+                    tokens.push((Token::MinusEquals, span));
+                } else {
+                    tokens.push((Token::Minus, span));
+                }
+            }
+            '*' => {
+                if let Some(&('=', _)) = iter.peek() {
+                    iter.next();
+                    tokens.push((Token::AsteriskEquals, span));
+                } else {
+                    tokens.push((Token::Asterisk, span));
+                }
+            }
+            '/' => {
+                if let Some(&('/', _)) = iter.peek() {
+                    iter.next();
+                    let mut comment = String::new();
+                    while let Some(&(ch, _)) = iter.peek() {
+                        if ch == '\n' {
+                            break;
+                        }
+                        comment.push(iter.next().unwrap().0);
+                    }
+                    tokens.push((Token::Comment(comment.trim_end().to_string()), span));
+                } else if let Some(&('=', _)) = iter.peek() {
+                    iter.next();
+                    tokens.push((Token::SlashEquals, span));
+                } else {
+                    tokens.push((Token::Slash, span));
+                }
+            }
+            '%' => {
+                if let Some(&('=', _)) = iter.peek() {
+                    iter.next();
+                    tokens.push((Token::PercentEquals, span));
+                } else {
+                    tokens.push((Token::Percent, span));
+                }
+            }
+            '^' => {
+                if let Some(&('=', _)) = iter.peek() {
+                    iter.next();
+                    tokens.push((Token::PowEquals, span));
+                } else {
+                    tokens.push((Token::Pow, span));
+                }
+            }
+            '(' => tokens.push((Token::LeftParen, span)),
+            ')' => tokens.push((Token::RightParen, span)),
+            '{' => tokens.push((Token::LeftBrace, span)),
+            '}' => tokens.push((Token::RightBrace, span)),
+            '[' => tokens.push((Token::LeftBracket, span)),
+            ']' => tokens.push((Token::RightBracket, span)),
             '"' => {
                 let mut string = String::new();
-                while let Some(&ch) = iter.peek() {
+                while let Some(&(ch, _)) = iter.peek() {
                     match ch {
                         '"' => {
                             iter.next();
                             break;
                         }
-                        _ => string.push(iter.next().unwrap()),
+                        _ => string.push(iter.next().unwrap().0),
                     }
                 }
-                tokens.push(Token::String(string));
+                tokens.push((Token::String(string), span));
             }
-            c if c.is_digit(10) => {
-                let mut number = String::new();
-                number.push(c);
-                while let Some(&ch) = iter.peek() {
-                    if ch.is_digit(10) {
-                        number.push(iter.next().unwrap());
+            '0' if matches!(iter.peek(), Some(&('x' | 'b', _))) => {
+                let (prefix, prefix_span) = iter.next().unwrap();
+                let (radix, suggestion) = match prefix {
+                    'x' => (16, "hexadecimal literals need 1 to 8 digits after 0x"),
+                    _ => (2, "binary literals need 1 to 32 digits after 0b"),
+                };
+
+                let mut digits = String::new();
+                while let Some(&(ch, _)) = iter.peek() {
+                    if ch.is_digit(radix) {
+                        digits.push(iter.next().unwrap().0);
                     } else {
                         break;
                     }
                 }
-                tokens.push(Token::Number(number.parse().unwrap()));
+
+                // literals cover all 32 bits, so 0xFFFFFFFF is -1
+                match u32::from_str_radix(&digits, radix) {
+                    Ok(number) => tokens.push((Token::Number(number as i32), span)),
+                    Err(_) => {
+                        return Err(LexError {
+                            character: prefix,
+                            span: prefix_span,
+                            suggestion: Some(suggestion),
+                        })
+                    }
+                }
             }
-            c if c.is_alphabetic() => {
+            c if c.is_ascii_digit() => {
+                let mut number = c.to_digit(10).unwrap() as i32;
+                while let Some(&(ch, digit_span)) = iter.peek() {
+                    let digit = match ch.to_digit(10) {
+                        Some(digit) => digit as i32,
+                        None => break,
+                    };
+                    iter.next();
+
+                    // the digit that takes the literal out of the i32 range is reported
+                    number = match number.checked_mul(10).and_then(|n| n.checked_add(digit)) {
+                        Some(number) => number,
+                        None => {
+                            return Err(LexError {
+                                character: ch,
+                                span: digit_span,
+                                suggestion: Some(
+                                    "integers must be at most 2147483647, write -2147483648 as 0x80000000",
+                                ),
+                            })
+                        }
+                    };
+                }
+                tokens.push((Token::Number(number), span));
+            }
+            c if c == '_' || c.is_xid_start() => {
                 let mut identifier = String::new();
                 identifier.push(c);
-                while let Some(&ch) = iter.peek() {
-                    if ch.is_alphabetic()  || ch == '_' {
-                        identifier.push(iter.next().unwrap());
+                while let Some(&(ch, _)) = iter.peek() {
+                    if ch.is_xid_continue() {
+                        identifier.push(iter.next().unwrap().0);
                     } else {
                         break;
                     }
                 }
                 match identifier.as_str() {
-                    "v" => tokens.push(Token::V),
-                    "w" => tokens.push(Token::W),
-                    "i" => tokens.push(Token::I),
-                    "f" => tokens.push(Token::F),
-                    "true" => tokens.push(Token::Boolean(true)),
-                    "false" => tokens.push(Token::Boolean(false)),
-                    "p" => tokens.push(Token::Print),
-                    "r" => tokens.push(Token::Return),
-                    _ => tokens.push(Token::Identifier(identifier)),
-                }
-            }
-            _ => tokens.push(Token::Unknown),
+                    "v" => tokens.push((Token::V, span)),
+                    "w" => tokens.push((Token::W, span)),
+                    "i" => tokens.push((Token::I, span)),
+                    "f" => tokens.push((Token::F, span)),
+                    "true" => tokens.push((Token::Boolean(true), span)),
+                    "false" => tokens.push((Token::Boolean(false), span)),
+                    "nil" => tokens.push((Token::Nil, span)),
+                    "p" => tokens.push((Token::Print, span)),
+                    "r" => tokens.push((Token::Return, span)),
+                    "t" => tokens.push((Token::Try, span)),
+                    "h" => tokens.push((Token::Catch, span)),
+                    "e" => tokens.push((Token::Raise, span)),
+                    _ => tokens.push((Token::Identifier(identifier), span)),
+                }
+            }
+            _ => return Err(LexError::new(ch, span)),
         }
     }
 
-    tokens.push(Token::EndOfFile);
-    tokens
+    tokens.push((Token::EndOfFile, end));
+    Ok(tokens)
 }
@@ -0,0 +1,392 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use super::{
+    ast::{ASTNode, TypeAnnotation},
+    diagnostic::Diagnostic,
+    stdlib::NATIVES,
+    tokenizer::{suggestion, Span, Token},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Int,
+    Str,
+    Bool,
+    List,
+    Function,
+    Nil,
+    // the type could not be inferred, e.g. a variable only declared further down
+    Unknown,
+}
+
+impl From<TypeAnnotation> for Type {
+    fn from(annotation: TypeAnnotation) -> Self {
+        match annotation {
+            TypeAnnotation::Int => Type::Int,
+            TypeAnnotation::Str => Type::Str,
+            TypeAnnotation::Bool => Type::Bool,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Type::Int => "int",
+            Type::Str => "str",
+            Type::Bool => "bool",
+            Type::List => "list",
+            Type::Function => "function",
+            Type::Nil => "nil",
+            Type::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// A variable keeps the type of its first declaration (or its annotation) for the whole
+// program; later declarations and assignments have to agree with it. Statements are
+// checked in source order, function bodies where they are declared with their parameters
+// and local variables visible only inside them.
+struct TypeChecker {
+    variables: HashMap<String, Type>,
+    // names declared in the function being checked, None outside of functions
+    locals: Option<HashSet<String>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl TypeChecker {
+    fn new() -> Self {
+        let variables = NATIVES
+            .iter()
+            .map(|native| (native.name.to_string(), Type::Function))
+            .collect();
+
+        Self {
+            variables,
+            locals: None,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn check(mut self, ast: &ASTNode) -> Vec<Diagnostic> {
+        self.check_statement(ast);
+        self.diagnostics
+    }
+
+    // statements
+
+    fn check_statement(&mut self, ast: &ASTNode) {
+        match ast {
+            ASTNode::Program(statements) => self.check_block(statements),
+            ASTNode::VariableDeclaration(name, annotation, value, span) => {
+                let value_type = self.infer(value);
+
+                let declared_type = match annotation {
+                    Some(annotation) => {
+                        let annotated_type = Type::from(*annotation);
+                        if !compatible(annotated_type, value_type) {
+                            self.diagnostics.push(Diagnostic::error(
+                                format!(
+                                    "Variable {} is annotated as {} but initialized with {}",
+                                    name, annotated_type, value_type
+                                ),
+                                *span,
+                            ));
+                        }
+                        annotated_type
+                    }
+                    None => value_type,
+                };
+
+                // the first declaration in a function shadows a variable of the same name outside
+                let shadows = self
+                    .locals
+                    .as_mut()
+                    .is_some_and(|locals| locals.insert(name.clone()));
+                if shadows {
+                    self.variables.insert(name.clone(), declared_type);
+                } else {
+                    self.bind(name, declared_type, *span);
+                }
+            }
+            ASTNode::VariableAssignment(name, value, span) => {
+                let value_type = self.infer(value);
+                self.bind(name, value_type, *span);
+            }
+            ASTNode::CompoundAssignment(name, operator, value, span) => {
+                let current = self.variables.get(name).copied().unwrap_or(Type::Unknown);
+                let value_type = self.infer(value);
+                // an operator error already explains the problem, binding would repeat it
+                let reported = self.diagnostics.len();
+                let result = self.infer_binary(current, operator, value_type, *span);
+                if self.diagnostics.len() == reported {
+                    self.bind(name, result, *span);
+                }
+            }
+            ASTNode::FunctionDeclaration(name, parameters, body, _) => {
+                self.variables.insert(name.clone(), Type::Function);
+                self.check_function(parameters, body);
+            }
+            ASTNode::FunctionCall(_, _) => {}
+            ASTNode::Print(expression, _) => {
+                self.infer(expression);
+            }
+            ASTNode::Return(Some(expression), _) => {
+                self.infer(expression);
+            }
+            ASTNode::If(condition, statements, span) => {
+                self.check_condition("i", condition, *span);
+                self.check_block(statements);
+            }
+            ASTNode::While(condition, statements, span) => {
+                self.check_condition("w", condition, *span);
+                self.check_block(statements);
+            }
+            ASTNode::Try(statements, name, handler, _) => {
+                self.check_block(statements);
+                // errors have no type of their own
+                self.variables.insert(name.clone(), Type::Unknown);
+                self.check_block(handler);
+            }
+            ASTNode::Raise(expression, span) => {
+                let raised_type = self.infer(expression);
+                if !compatible(Type::Str, raised_type) {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("e expects a str or an error but got {}", raised_type),
+                        *span,
+                    ));
+                }
+            }
+            _ => {
+                self.infer(ast);
+            }
+        }
+    }
+
+    fn check_function(&mut self, parameters: &[String], body: &[ASTNode]) {
+        let outer = self.variables.clone();
+        for parameter in parameters {
+            self.variables.insert(parameter.clone(), Type::Unknown);
+        }
+        let outer_locals = self.locals.replace(parameters.iter().cloned().collect());
+        self.check_block(body);
+        self.variables = outer;
+        self.locals = outer_locals;
+    }
+
+    fn check_block(&mut self, statements: &[ASTNode]) {
+        for statement in statements {
+            self.check_statement(statement);
+        }
+    }
+
+    fn check_condition(&mut self, keyword: &str, condition: &ASTNode, span: Span) {
+        let condition_type = self.infer(condition);
+        if !compatible(Type::Bool, condition_type) {
+            self.diagnostics.push(Diagnostic::error(
+                format!(
+                    "Condition of {} must be bool but is {}",
+                    keyword, condition_type
+                ),
+                span,
+            ));
+        }
+    }
+
+    fn bind(&mut self, name: &str, value_type: Type, span: Span) {
+        match self.variables.get(name).copied() {
+            // a variable first declared as nil takes the type of its first real value
+            Some(existing) if existing != Type::Unknown && existing != Type::Nil => {
+                if !compatible(existing, value_type) {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!(
+                            "Variable {} has type {} but is assigned {}",
+                            name, existing, value_type
+                        ),
+                        span,
+                    ));
+                }
+            }
+            _ => {
+                self.variables.insert(name.to_string(), value_type);
+            }
+        }
+    }
+
+    // expressions
+
+    fn infer(&mut self, ast: &ASTNode) -> Type {
+        match ast {
+            ASTNode::Number(_) => Type::Int,
+            ASTNode::String(_) => Type::Str,
+            ASTNode::Boolean(_) => Type::Bool,
+            ASTNode::Nil => Type::Nil,
+            ASTNode::Identifier(name, _) => {
+                self.variables.get(name).copied().unwrap_or(Type::Unknown)
+            }
+            ASTNode::Binary(left, operator, right, span) => {
+                let left_type = self.infer(left);
+                let right_type = self.infer(right);
+                self.infer_binary(left_type, operator, right_type, *span)
+            }
+            ASTNode::Unary(operator, right, span) => {
+                let right_type = self.infer(right);
+                self.infer_unary(operator, right_type, *span)
+            }
+            ASTNode::List(elements, _) => {
+                for element in elements {
+                    self.infer(element);
+                }
+                Type::List
+            }
+            ASTNode::Lambda(parameters, body, _) => {
+                self.check_function(parameters, body);
+                Type::Function
+            }
+            ASTNode::Call(callee, arguments, span) => {
+                let callee_type = self.infer(callee);
+                for argument in arguments {
+                    self.infer(argument);
+                }
+                if !compatible(Type::Function, callee_type) {
+                    self.diagnostics.push(Diagnostic::error(
+                        format!("A value of type {} cannot be called", callee_type),
+                        *span,
+                    ));
+                }
+                Type::Unknown
+            }
+            _ => Type::Unknown,
+        }
+    }
+
+    fn infer_binary(&mut self, left: Type, operator: &Token, right: Type, span: Span) -> Type {
+        let (operands, result) = match operator {
+            // the default is used when the value is nil, so the result can be either
+            Token::QuestionQuestion => {
+                return match left {
+                    Type::Nil => right,
+                    left if left == right => left,
+                    _ => Type::Unknown,
+                };
+            }
+            Token::Plus => {
+                let result = match (left, right) {
+                    (Type::Str, _) | (_, Type::Str) => Type::Str,
+                    (Type::Int, _) | (_, Type::Int) => Type::Int,
+                    _ => Type::Unknown,
+                };
+                (&[Type::Int, Type::Str][..], result)
+            }
+            Token::Minus
+            | Token::Asterisk
+            | Token::Slash
+            | Token::Percent
+            | Token::Pow
+            | Token::BitAnd
+            | Token::BitOr
+            | Token::Tilde
+            | Token::ShiftLeft
+            | Token::ShiftRight => (&[Type::Int][..], Type::Int),
+            Token::EqualEqual | Token::Bang | Token::NotEqual => (
+                &[Type::Int, Type::Str, Type::Bool, Type::Nil][..],
+                Type::Bool,
+            ),
+            Token::LessThan
+            | Token::LessThanEquals
+            | Token::GreaterThan
+            | Token::GreaterThanEquals => (&[Type::Int][..], Type::Bool),
+            Token::And | Token::Or => (&[Type::Bool][..], Type::Bool),
+            _ => return Type::Unknown,
+        };
+
+        let accepted = |operand: Type| operand == Type::Unknown || operands.contains(&operand);
+        if !accepted(left) || !accepted(right) || !compatible(left, right) {
+            let mut message = format!(
+                "Operator {} cannot be applied to {} and {}",
+                operator_symbol(operator),
+                left,
+                right
+            );
+            if let (Token::BitAnd | Token::BitOr, Type::Bool, Type::Bool) = (operator, left, right)
+            {
+                if let Some(hint) = operator_symbol(operator)
+                    .chars()
+                    .next()
+                    .and_then(suggestion)
+                {
+                    message = format!("{} ({})", message, hint);
+                }
+            }
+            self.diagnostics.push(Diagnostic::error(message, span));
+        }
+
+        result
+    }
+
+    fn infer_unary(&mut self, operator: &Token, right: Type, span: Span) -> Type {
+        let expected = match operator {
+            Token::Minus | Token::Tilde => Type::Int,
+            Token::Bang => Type::Bool,
+            _ => return Type::Unknown,
+        };
+
+        if !compatible(expected, right) {
+            self.diagnostics.push(Diagnostic::error(
+                format!(
+                    "Operator {} cannot be applied to {}",
+                    operator_symbol(operator),
+                    right
+                ),
+                span,
+            ));
+        }
+
+        expected
+    }
+}
+
+// any variable can hold nil, and nil can be compared with anything
+fn compatible(expected: Type, actual: Type) -> bool {
+    expected == actual
+        || expected == Type::Unknown
+        || actual == Type::Unknown
+        || expected == Type::Nil
+        || actual == Type::Nil
+}
+
+fn operator_symbol(operator: &Token) -> &'static str {
+    match operator {
+        Token::Plus => "+",
+        Token::Minus => "-",
+        Token::Asterisk => "*",
+        Token::Slash => "/",
+        Token::Percent => "%",
+        Token::Pow => "^",
+        Token::EqualEqual => "==",
+        Token::Bang => "!",
+        Token::NotEqual => "!=",
+        Token::LessThan => "<",
+        Token::LessThanEquals => "<=",
+        Token::GreaterThan => ">",
+        Token::GreaterThanEquals => ">=",
+        Token::And => "&&",
+        Token::Or => "||",
+        Token::BitAnd => "&",
+        Token::BitOr => "|",
+        Token::Tilde => "~",
+        Token::QuestionQuestion => "??",
+        Token::ShiftLeft => "<<",
+        Token::ShiftRight => ">>",
+        _ => "?",
+    }
+}
+
+pub fn check(ast: &ASTNode) -> Vec<Diagnostic> {
+    let checker = TypeChecker::new();
+    checker.check(ast)
+}
@@ -0,0 +1,80 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use super::{ast::ASTNode, environment::Environment, stdlib::Native};
+
+#[derive(Debug, Clone)]
+pub enum Value {
+    Number(i32),
+    String(String),
+    Boolean(bool),
+    // no value, what functions without r return
+    Nil,
+    List(Rc<Vec<Value>>),
+    Function(Rc<Function>),
+    Native(&'static Native),
+    Error(Rc<ErrorValue>),
+}
+
+impl Value {
+    pub fn error(kind: &str, message: String) -> Self {
+        Value::Error(Rc::new(ErrorValue {
+            kind: kind.to_string(),
+            message,
+        }))
+    }
+
+    // names match the types of the type checker
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "int",
+            Value::String(_) => "str",
+            Value::Boolean(_) => "bool",
+            Value::Nil => "nil",
+            Value::List(_) => "list",
+            Value::Function(_) | Value::Native(_) => "function",
+            Value::Error(_) => "error",
+        }
+    }
+}
+
+// a failure a script can handle itself, like a missing file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorValue {
+    pub kind: String,
+    pub message: String,
+}
+
+// values of different types are never equal, and functions only equal themselves
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Number(left), Value::Number(right)) => left == right,
+            (Value::String(left), Value::String(right)) => left == right,
+            (Value::Boolean(left), Value::Boolean(right)) => left == right,
+            (Value::Nil, Value::Nil) => true,
+            (Value::List(left), Value::List(right)) => left == right,
+            (Value::Function(left), Value::Function(right)) => Rc::ptr_eq(left, right),
+            (Value::Native(left), Value::Native(right)) => std::ptr::eq(*left, *right),
+            (Value::Error(left), Value::Error(right)) => left == right,
+            _ => false,
+        }
+    }
+}
+
+pub struct Function {
+    // None for lambdas
+    pub name: Option<String>,
+    pub parameters: Vec<String>,
+    pub body: Rc<Vec<ASTNode>>,
+    pub environment: Rc<RefCell<Environment>>,
+}
+
+// the environment can contain the function itself, so it is left out
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Function")
+            .field("name", &self.name)
+            .field("parameters", &self.parameters)
+            .finish()
+    }
+}
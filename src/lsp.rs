@@ -0,0 +1,648 @@
+use std::{collections::HashMap, error::Error};
+
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::{
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as NotificationTrait, PublishDiagnostics,
+    },
+    request::{
+        Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as RequestTrait,
+    },
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse,
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, Position,
+    PublishDiagnosticsParams, Range, ServerCapabilities, SymbolKind, TextDocumentSyncCapability,
+    TextDocumentSyncKind, Uri,
+};
+
+use crate::logic::{
+    ast::{parse_partial, ASTNode, TypeAnnotation},
+    diagnostic::{Diagnostic, Severity},
+    resolver::resolve,
+    tokenizer::{tokenize, Span, Token},
+};
+
+const KEYWORDS: [&str; 12] = [
+    "v", "w", "i", "f", "p", "r", "t", "h", "e", "true", "false", "nil",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DeclarationKind {
+    Variable,
+    Function,
+}
+
+#[derive(Debug, Clone)]
+struct Declaration {
+    name: String,
+    kind: DeclarationKind,
+    annotation: Option<TypeAnnotation>,
+    parameters: Vec<String>,
+    span: Span,
+    // declarations inside a function body
+    children: Vec<Declaration>,
+    // braces around a function body
+    body: Option<(Span, Span)>,
+}
+
+impl Declaration {
+    fn encloses(&self, position: Span) -> bool {
+        self.body
+            .is_some_and(|(start, end)| start < position && position <= end)
+    }
+}
+
+struct Document {
+    text: String,
+    comments: Vec<(String, Span)>,
+    declarations: Vec<Declaration>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Document {
+    fn new(text: String) -> Self {
+        let mut document = Self {
+            text: String::new(),
+            comments: Vec::new(),
+            declarations: Vec::new(),
+            diagnostics: Vec::new(),
+        };
+        document.update(text);
+        document
+    }
+
+    fn update(&mut self, text: String) {
+        self.text = text;
+
+        // symbols of the previous version stay usable until the text tokenizes again
+        let tokens = match tokenize(&self.text) {
+            Ok(tokens) => tokens,
+            Err(error) => {
+                self.diagnostics = vec![error.into()];
+                return;
+            }
+        };
+        self.comments = tokens
+            .iter()
+            .filter_map(|(token, span)| match token {
+                Token::Comment(comment) => Some((comment.clone(), *span)),
+                _ => None,
+            })
+            .collect();
+
+        let blocks = blocks(&tokens);
+
+        // name resolution on a partial program would report names declared in broken statements
+        let (ast, diagnostics) = parse_partial(tokens);
+        self.diagnostics = if diagnostics.is_empty() {
+            resolve(&ast)
+        } else {
+            diagnostics
+        };
+        self.declarations = collect_declarations(&ast);
+        set_bodies(&mut self.declarations, &blocks);
+    }
+
+    // all declarations, including the ones nested in function bodies
+    fn all_declarations(&self) -> Vec<&Declaration> {
+        fn walk<'a>(declarations: &'a [Declaration], found: &mut Vec<&'a Declaration>) {
+            for declaration in declarations {
+                found.push(declaration);
+                walk(&declaration.children, found);
+            }
+        }
+
+        let mut found = Vec::new();
+        walk(&self.declarations, &mut found);
+        found
+    }
+
+    // the declaration a name at the given position refers to: the closest one above it,
+    // or the first one if the name is only declared further down
+    fn definition(&self, position: Position) -> Option<&Declaration> {
+        let (name, span) = self.word_at(position)?;
+        let mut candidates: Vec<&Declaration> = self
+            .all_declarations()
+            .into_iter()
+            .filter(|declaration| declaration.name == name)
+            .collect();
+        candidates.sort_by_key(|declaration| declaration.span);
+
+        candidates
+            .iter()
+            .rev()
+            .find(|declaration| declaration.span <= span)
+            .or_else(|| candidates.first())
+            .copied()
+    }
+
+    // consecutive comment lines directly above a declaration
+    fn doc_comment(&self, span: Span) -> Option<String> {
+        let mut lines = Vec::new();
+        let mut line = span.line;
+
+        while line > 1 {
+            line -= 1;
+            let comment = self.comments.iter().find(|(_, comment_span)| {
+                comment_span.line == line && self.line_before(*comment_span).trim().is_empty()
+            });
+            match comment {
+                Some((comment, _)) => lines.push(comment.trim().to_string()),
+                None => break,
+            }
+        }
+
+        if lines.is_empty() {
+            return None;
+        }
+        lines.reverse();
+        Some(lines.join("\n"))
+    }
+
+    fn line_before(&self, span: Span) -> String {
+        self.text
+            .lines()
+            .nth(span.line - 1)
+            .unwrap_or_default()
+            .chars()
+            .take(span.column - 1)
+            .collect()
+    }
+
+    fn word_at(&self, position: Position) -> Option<(String, Span)> {
+        let line = self.text.lines().nth(position.line as usize)?;
+        let chars: Vec<char> = line.chars().collect();
+        let cursor = utf16_to_column(line, position.character);
+
+        let is_word = |ch: &char| ch.is_alphanumeric() || *ch == '_';
+        let mut start = cursor.min(chars.len());
+        while start > 0 && is_word(&chars[start - 1]) {
+            start -= 1;
+        }
+        let mut end = cursor.min(chars.len());
+        while end < chars.len() && is_word(&chars[end]) {
+            end += 1;
+        }
+
+        if start == end {
+            return None;
+        }
+        let word: String = chars[start..end].iter().collect();
+        Some((word, Span::new(position.line as usize + 1, start + 1)))
+    }
+
+    fn range(&self, span: Span, length: usize) -> Range {
+        let line = self
+            .text
+            .lines()
+            .nth(span.line.saturating_sub(1))
+            .unwrap_or_default();
+        let start = column_to_utf16(line, span.column.saturating_sub(1));
+        let end = column_to_utf16(line, span.column.saturating_sub(1) + length);
+        let line = span.line.saturating_sub(1) as u32;
+
+        Range::new(Position::new(line, start), Position::new(line, end))
+    }
+
+    // highlights the word starting at a span, or a single character
+    fn word_range(&self, span: Span) -> Range {
+        let length = self
+            .text
+            .lines()
+            .nth(span.line.saturating_sub(1))
+            .map(|line| {
+                line.chars()
+                    .skip(span.column.saturating_sub(1))
+                    .take_while(|ch| ch.is_alphanumeric() || *ch == '_')
+                    .count()
+            })
+            .unwrap_or_default();
+        self.range(span, length.max(1))
+    }
+}
+
+struct Server {
+    documents: HashMap<String, Document>,
+}
+
+impl Server {
+    fn new() -> Self {
+        Self {
+            documents: HashMap::new(),
+        }
+    }
+
+    // requests
+
+    fn handle_request(&mut self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            GotoDefinition::METHOD => self.with_params(request, Self::definition),
+            HoverRequest::METHOD => self.with_params(request, Self::hover),
+            Completion::METHOD => self.with_params(request, Self::completion),
+            DocumentSymbolRequest::METHOD => self.with_params(request, Self::document_symbols),
+            _ => {
+                return Response::new_err(
+                    id,
+                    lsp_server::ErrorCode::MethodNotFound as i32,
+                    format!("Unsupported request {}", request.method),
+                )
+            }
+        };
+
+        match result {
+            Ok(result) => Response::new_ok(id, result),
+            Err(e) => Response::new_err(id, lsp_server::ErrorCode::InvalidParams as i32, e),
+        }
+    }
+
+    fn with_params<P, R>(
+        &self,
+        request: Request,
+        handler: fn(&Self, P) -> Option<R>,
+    ) -> Result<serde_json::Value, String>
+    where
+        P: serde::de::DeserializeOwned,
+        R: serde::Serialize,
+    {
+        let params = serde_json::from_value(request.params).map_err(|e| e.to_string())?;
+        serde_json::to_value(handler(self, params)).map_err(|e| e.to_string())
+    }
+
+    fn document(&self, uri: &Uri) -> Option<&Document> {
+        self.documents.get(uri.as_str())
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let document = self.document(&uri)?;
+        let declaration = document.definition(params.text_document_position_params.position)?;
+
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            uri.clone(),
+            document.word_range(declaration.span),
+        )))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let document = self.document(&params.text_document_position_params.text_document.uri)?;
+        let declaration = document.definition(params.text_document_position_params.position)?;
+
+        let signature = match (declaration.kind, declaration.annotation) {
+            (DeclarationKind::Function, _) if declaration.parameters.is_empty() => {
+                format!("f {}", declaration.name)
+            }
+            (DeclarationKind::Function, _) => format!(
+                "f {}({})",
+                declaration.name,
+                declaration.parameters.join(", ")
+            ),
+            (DeclarationKind::Variable, Some(annotation)) => {
+                format!("v {}: {}", declaration.name, annotation_name(annotation))
+            }
+            (DeclarationKind::Variable, None) => format!("v {}", declaration.name),
+        };
+        let mut value = format!("```netl2\n{}\n```", signature);
+        if let Some(doc) = document.doc_comment(declaration.span) {
+            value.push_str(&format!("\n\n{}", doc));
+        }
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: None,
+        })
+    }
+
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let document = self.document(&params.text_document_position.text_document.uri)?;
+        let position = params.text_document_position.position;
+        let line = document
+            .text
+            .lines()
+            .nth(position.line as usize)
+            .unwrap_or_default();
+        let cursor = Span::new(
+            position.line as usize + 1,
+            utf16_to_column(line, position.character) + 1,
+        );
+
+        let mut items: Vec<CompletionItem> = KEYWORDS
+            .iter()
+            .map(|keyword| CompletionItem {
+                label: keyword.to_string(),
+                kind: Some(CompletionItemKind::KEYWORD),
+                ..Default::default()
+            })
+            .collect();
+
+        // locals and parameters are only visible inside their function, innermost first
+        let mut scopes: Vec<(&[Declaration], &[String])> = vec![(&document.declarations, &[])];
+        while let Some(function) = scopes.last().and_then(|(declarations, _)| {
+            declarations
+                .iter()
+                .find(|declaration| declaration.encloses(cursor))
+        }) {
+            scopes.push((&function.children, &function.parameters));
+        }
+
+        let mut seen: Vec<&String> = Vec::new();
+        for (declarations, parameters) in scopes.into_iter().rev() {
+            for parameter in parameters {
+                if seen.contains(&parameter) {
+                    continue;
+                }
+                seen.push(parameter);
+
+                items.push(CompletionItem {
+                    label: parameter.clone(),
+                    kind: Some(CompletionItemKind::VARIABLE),
+                    ..Default::default()
+                });
+            }
+
+            // names are only usable once their declaration has run
+            for declaration in declarations {
+                if declaration.span >= cursor || seen.contains(&&declaration.name) {
+                    continue;
+                }
+                seen.push(&declaration.name);
+
+                let kind = match declaration.kind {
+                    DeclarationKind::Variable => CompletionItemKind::VARIABLE,
+                    DeclarationKind::Function => CompletionItemKind::FUNCTION,
+                };
+                items.push(CompletionItem {
+                    label: declaration.name.clone(),
+                    kind: Some(kind),
+                    documentation: document
+                        .doc_comment(declaration.span)
+                        .map(lsp_types::Documentation::String),
+                    ..Default::default()
+                });
+            }
+        }
+
+        Some(CompletionResponse::Array(items))
+    }
+
+    fn document_symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let document = self.document(&params.text_document.uri)?;
+        let symbols = document
+            .declarations
+            .iter()
+            .map(|declaration| document_symbol(document, declaration))
+            .collect();
+
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+
+    // notifications
+
+    fn handle_notification(&mut self, notification: Notification) -> Option<Uri> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                let uri = params.text_document.uri;
+                self.documents
+                    .insert(uri.to_string(), Document::new(params.text_document.text));
+                Some(uri)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                let uri = params.text_document.uri;
+                // the server asks for full syncs, so the last change holds the whole text
+                let text = params.content_changes.into_iter().last()?.text;
+                match self.documents.get_mut(uri.as_str()) {
+                    Some(document) => document.update(text),
+                    None => {
+                        self.documents.insert(uri.to_string(), Document::new(text));
+                    }
+                }
+                Some(uri)
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                self.documents.remove(params.text_document.uri.as_str());
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn diagnostics(&self, uri: Uri) -> Notification {
+        let diagnostics = self
+            .document(&uri)
+            .map(|document| {
+                document
+                    .diagnostics
+                    .iter()
+                    .map(|diagnostic| lsp_diagnostic(document, diagnostic))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            PublishDiagnosticsParams::new(uri, diagnostics, None),
+        )
+    }
+}
+
+fn collect_declarations(ast: &ASTNode) -> Vec<Declaration> {
+    let mut declarations = Vec::new();
+    match ast {
+        ASTNode::Program(statements)
+        | ASTNode::If(_, statements, _)
+        | ASTNode::While(_, statements, _) => {
+            for statement in statements {
+                declarations.extend(collect_declarations(statement));
+            }
+        }
+        ASTNode::Try(statements, _, handler, _) => {
+            for statement in statements.iter().chain(handler) {
+                declarations.extend(collect_declarations(statement));
+            }
+        }
+        ASTNode::VariableDeclaration(name, annotation, _, span) => declarations.push(Declaration {
+            name: name.clone(),
+            kind: DeclarationKind::Variable,
+            annotation: *annotation,
+            parameters: Vec::new(),
+            span: *span,
+            children: Vec::new(),
+            body: None,
+        }),
+        ASTNode::FunctionDeclaration(name, parameters, body, span) => {
+            declarations.push(Declaration {
+                name: name.clone(),
+                kind: DeclarationKind::Function,
+                annotation: None,
+                parameters: parameters.clone(),
+                span: *span,
+                children: body.iter().flat_map(collect_declarations).collect(),
+                body: None,
+            })
+        }
+        _ => {}
+    }
+    declarations
+}
+
+// opening and closing brace of every block, ordered by where the blocks start; a block
+// left open ends at the end of the file
+fn blocks(tokens: &[(Token, Span)]) -> Vec<(Span, Span)> {
+    let end = tokens.last().map(|(_, span)| *span).unwrap_or_default();
+    let mut blocks = Vec::new();
+    let mut open = Vec::new();
+
+    for (token, span) in tokens {
+        match token {
+            Token::LeftBrace => {
+                open.push(blocks.len());
+                blocks.push((*span, end));
+            }
+            Token::RightBrace => {
+                if let Some(block) = open.pop() {
+                    blocks[block].1 = *span;
+                }
+            }
+            _ => {}
+        }
+    }
+    blocks
+}
+
+// a function body is the first block after the function name, as parameters hold no braces
+fn set_bodies(declarations: &mut [Declaration], blocks: &[(Span, Span)]) {
+    for declaration in declarations {
+        if declaration.kind == DeclarationKind::Function {
+            declaration.body = blocks
+                .iter()
+                .find(|(start, _)| *start > declaration.span)
+                .copied();
+        }
+        set_bodies(&mut declaration.children, blocks);
+    }
+}
+
+#[allow(deprecated)]
+fn document_symbol(document: &Document, declaration: &Declaration) -> DocumentSymbol {
+    let kind = match declaration.kind {
+        DeclarationKind::Variable => SymbolKind::VARIABLE,
+        DeclarationKind::Function => SymbolKind::FUNCTION,
+    };
+    let range = document.word_range(declaration.span);
+    let children: Vec<DocumentSymbol> = declaration
+        .children
+        .iter()
+        .map(|child| document_symbol(document, child))
+        .collect();
+
+    DocumentSymbol {
+        name: declaration.name.clone(),
+        detail: None,
+        kind,
+        tags: None,
+        deprecated: None,
+        range,
+        selection_range: range,
+        children: if children.is_empty() {
+            None
+        } else {
+            Some(children)
+        },
+    }
+}
+
+fn lsp_diagnostic(document: &Document, diagnostic: &Diagnostic) -> lsp_types::Diagnostic {
+    let severity = match diagnostic.severity {
+        Severity::Error => DiagnosticSeverity::ERROR,
+        Severity::Warning => DiagnosticSeverity::WARNING,
+    };
+
+    lsp_types::Diagnostic {
+        range: document.word_range(diagnostic.span),
+        severity: Some(severity),
+        code: diagnostic
+            .code
+            .map(|code| lsp_types::NumberOrString::String(code.to_string())),
+        source: Some("netl2".to_string()),
+        message: diagnostic.message.clone(),
+        ..Default::default()
+    }
+}
+
+fn annotation_name(annotation: TypeAnnotation) -> &'static str {
+    match annotation {
+        TypeAnnotation::Int => "int",
+        TypeAnnotation::Str => "str",
+        TypeAnnotation::Bool => "bool",
+    }
+}
+
+// spans count characters while LSP positions count UTF-16 code units
+fn column_to_utf16(line: &str, column: usize) -> u32 {
+    line.chars()
+        .take(column)
+        .map(char::len_utf16)
+        .sum::<usize>() as u32
+}
+
+fn utf16_to_column(line: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (column, ch) in line.chars().enumerate() {
+        if units >= character as usize {
+            return column;
+        }
+        units += ch.len_utf16();
+    }
+    line.chars().count()
+}
+
+pub fn run() -> Result<(), Box<dyn Error + Sync + Send>> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = serde_json::to_value(ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..Default::default()
+    })?;
+    connection.initialize(capabilities)?;
+
+    let mut server = Server::new();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                let response = server.handle_request(request);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                if let Some(uri) = server.handle_notification(notification) {
+                    let diagnostics = server.diagnostics(uri);
+                    connection.sender.send(Message::Notification(diagnostics))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+
+    // the writer thread only finishes once every sender is gone
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
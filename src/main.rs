@@ -1,33 +1,380 @@
-use crate::logic::{ast::parse, interpeter::interpret};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-mod logic;
+use netl2::logic::{
+    ast::{parse, ASTNode},
+    capabilities::{Capabilities, Capability},
+    diagnostic::Diagnostic,
+    error::RuntimeError,
+    formatter,
+    interpeter::Interpreter,
+    limits::Limits,
+    linter,
+    optimizer::optimize,
+    resolver::resolve,
+    tester::run_tests,
+    tokenizer::tokenize,
+    typechecker,
+};
 
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        Some("check") => check(&args[1..]),
+        Some("fmt") => fmt(&args[1..]),
+        Some("lint") => lint(&args[1..]),
+        Some("test") => test(&args[1..]),
+        _ => run(&args),
+    }
+}
+
+fn run(args: &[String]) {
+    // flags up to the script path are for NETL2, everything after it is for the script
+    let script = args
+        .iter()
+        .position(|arg| !arg.starts_with("--"))
+        .map_or(args.len(), |script| script + 1);
+    let (args, script_args) = args.split_at(script);
+
+    let dump_ast = args.iter().any(|arg| arg == "--dump-ast");
+    let (limits, capabilities) = match (limits(args), capabilities(args)) {
+        (Some(limits), Some(capabilities)) => (limits, capabilities),
+        _ => std::process::exit(1),
+    };
+    let parsed = match load(args) {
+        Some(parsed) => parsed,
+        None => std::process::exit(1),
+    };
+
+    let diagnostics = resolve(&parsed);
+    if report(&diagnostics) {
+        std::process::exit(1);
+    }
+
+    let optimized = optimize(parsed);
+
+    if dump_ast {
+        println!("{:#?}", optimized);
+        return;
+    }
+
+    let mut interpreter = Interpreter::new()
+        .with_limits(limits)
+        .with_capabilities(capabilities)
+        .with_arguments(script_args.to_vec());
+    if let Err(error) = interpreter.run(&optimized) {
+        println!("{}", error);
+    }
+
+    // exiting skips destructors, so buffered output is written first
+    let _ = std::io::stdout().flush();
+    std::process::exit(interpreter.exit_code());
+}
+
+fn check(args: &[String]) {
+    let parsed = match load(args) {
+        Some(parsed) => parsed,
+        None => std::process::exit(1),
+    };
+
+    let mut diagnostics = resolve(&parsed);
+    diagnostics.extend(typechecker::check(&parsed));
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span);
+
+    if report(&diagnostics) {
+        std::process::exit(1);
+    }
+}
+
+fn fmt(args: &[String]) {
+    let check_only = args.iter().any(|arg| arg == "--check");
+    let paths: Vec<&String> = args.iter().filter(|arg| !arg.starts_with("--")).collect();
+    if paths.is_empty() {
+        println!("Error: No file path provided");
+        std::process::exit(1);
+    }
+
+    let mut failed = false;
+    for path in paths {
+        let script = match std::fs::read_to_string(path) {
+            Ok(script) => script,
+            Err(e) => {
+                println!("Error: {}: {}", path, e);
+                failed = true;
+                continue;
+            }
+        };
+
+        let formatted = match formatter::format(&script) {
+            Ok(formatted) => formatted,
+            Err(diagnostics) => {
+                for diagnostic in &diagnostics {
+                    println!("{}: {}", path, diagnostic);
+                }
+                failed = true;
+                continue;
+            }
+        };
+
+        if formatted == script {
+            continue;
+        }
+
+        if check_only {
+            println!("{} is not formatted", path);
+            failed = true;
+        } else if let Err(e) = std::fs::write(path, formatted) {
+            println!("Error: {}: {}", path, e);
+            failed = true;
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+fn lint(args: &[String]) {
+    if args.is_empty() {
+        println!("Error: No file path provided");
+        std::process::exit(1);
+    }
+
+    let mut failed = false;
+    for path in args {
+        let script = match std::fs::read_to_string(path) {
+            Ok(script) => script,
+            Err(e) => {
+                println!("Error: {}: {}", path, e);
+                failed = true;
+                continue;
+            }
+        };
+
+        match linter::lint(&script) {
+            Ok(diagnostics) => {
+                for diagnostic in &diagnostics {
+                    println!("{}: {}", path, diagnostic);
+                }
+                failed |= !diagnostics.is_empty();
+            }
+            Err(diagnostics) => {
+                for diagnostic in &diagnostics {
+                    println!("{}: {}", path, diagnostic);
+                }
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+// runs the test functions of the given files, and of the .nl files in given directories
+fn test(args: &[String]) {
+    let mut paths = Vec::new();
+    for arg in args.iter().filter(|arg| !arg.starts_with("--")) {
+        collect_scripts(&PathBuf::from(arg), &mut paths);
+    }
+    if paths.is_empty() {
         println!("Error: No file path provided");
+        std::process::exit(1);
+    }
+    let (limits, capabilities) = match (limits(args), capabilities(args)) {
+        (Some(limits), Some(capabilities)) => (limits, capabilities),
+        _ => std::process::exit(1),
+    };
+
+    let (mut passed, mut failed) = (0, 0);
+    for path in paths {
+        let parsed = match load(&[path.display().to_string()]) {
+            Some(parsed) => parsed,
+            None => {
+                failed += 1;
+                continue;
+            }
+        };
+        if report(&resolve(&parsed)) {
+            failed += 1;
+            continue;
+        }
+
+        for test in run_tests(&parsed, limits, &capabilities) {
+            match test.result {
+                Ok(()) => {
+                    println!("test {}: {} ... ok", path.display(), test.name);
+                    passed += 1;
+                }
+                Err(error) => {
+                    println!("test {}: {} ... FAILED", path.display(), test.name);
+                    match error {
+                        RuntimeError::Exit(code, _) => {
+                            println!("Error: Test exited with code {}", code)
+                        }
+                        error => println!("{}", error),
+                    }
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    println!("\n{} passed, {} failed", passed, failed);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn collect_scripts(path: &Path, scripts: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        scripts.push(path.to_path_buf());
         return;
     }
 
-    let path = &args[1];
+    let mut entries: Vec<PathBuf> = match std::fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .collect(),
+        Err(e) => {
+            println!("Error: {}: {}", path.display(), e);
+            return;
+        }
+    };
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() || entry.extension().is_some_and(|extension| extension == "nl") {
+            collect_scripts(&entry, scripts);
+        }
+    }
+}
+
+fn limits(args: &[String]) -> Option<Limits> {
+    let mut limits = Limits::default();
+    for arg in args {
+        let (flag, value) = match arg.split_once('=') {
+            Some(flag) => flag,
+            None => continue,
+        };
+
+        let parsed = match flag {
+            "--max-depth" => value.parse().map(|depth| limits.max_depth = depth),
+            "--max-steps" => value.parse().map(|steps| limits.max_steps = Some(steps)),
+            "--timeout" => value
+                .parse()
+                .map(|millis| limits.timeout = Some(Duration::from_millis(millis))),
+            "--max-memory" => value.parse().map(|bytes| limits.max_memory = Some(bytes)),
+            _ => continue,
+        };
+        if parsed.is_err() {
+            println!("Error: Invalid value {} for {}", value, flag);
+            return None;
+        }
+    }
+    Some(limits)
+}
+
+// --allow-<capability> and --deny-<capability>, where read and write can be limited to
+// some directories as in --allow-read=data,logs
+fn capabilities(args: &[String]) -> Option<Capabilities> {
+    let mut capabilities = Capabilities::default();
+    for arg in args {
+        if arg == "--allow-all" {
+            capabilities = Capabilities::all();
+            continue;
+        }
+
+        let (allow, flag) = match (arg.strip_prefix("--allow-"), arg.strip_prefix("--deny-")) {
+            (Some(flag), _) => (true, flag),
+            (_, Some(flag)) => (false, flag),
+            _ => continue,
+        };
+        let (name, paths) = match flag.split_once('=') {
+            Some((name, paths)) => (name, Some(paths)),
+            None => (flag, None),
+        };
+
+        let capability = match capability(name) {
+            Some(capability) => capability,
+            None => {
+                println!("Error: Unknown capability {}", name);
+                return None;
+            }
+        };
+
+        capabilities = match (allow, paths) {
+            (true, None) => capabilities.allow(capability),
+            (true, Some(paths))
+                if matches!(capability, Capability::FsRead | Capability::FsWrite)
+                    && !paths.is_empty() =>
+            {
+                capabilities.allow_paths(capability, paths.split(',').map(PathBuf::from).collect())
+            }
+            (false, None) => capabilities.deny(capability),
+            _ => {
+                println!("Error: Invalid flag {}", arg);
+                return None;
+            }
+        };
+    }
+    Some(capabilities)
+}
+
+fn capability(name: &str) -> Option<Capability> {
+    match name {
+        "read" => Some(Capability::FsRead),
+        "write" => Some(Capability::FsWrite),
+        name => Capability::ALL
+            .into_iter()
+            .find(|capability| capability.name() == name),
+    }
+}
+
+// reads and parses the script named by the first non-flag argument
+fn load(args: &[String]) -> Option<ASTNode> {
+    let path = match args.iter().find(|arg| !arg.starts_with("--")) {
+        Some(path) => path,
+        None => {
+            println!("Error: No file path provided");
+            return None;
+        }
+    };
 
     let script = match std::fs::read_to_string(path) {
         Ok(script) => script,
         Err(e) => {
             println!("Error: {}", e);
-            return;
+            return None;
         }
     };
 
-    let tokens = logic::tokenizer::tokenize(&script);
-
-    let parsed = match parse(tokens) {
-        Ok(parsed) => parsed,
+    let tokens = match tokenize(&script) {
+        Ok(tokens) => tokens,
         Err(e) => {
-            println!("Error: {}", e);
-            return;
+            println!("{}", e);
+            return None;
         }
     };
 
-    interpret(parsed);
+    match parse(tokens) {
+        Ok(parsed) => Some(parsed),
+        Err(diagnostics) => {
+            report(&diagnostics);
+            None
+        }
+    }
+}
+
+// prints diagnostics and returns whether any of them is an error
+fn report(diagnostics: &[Diagnostic]) -> bool {
+    for diagnostic in diagnostics {
+        println!("{}", diagnostic);
+    }
+    diagnostics.iter().any(Diagnostic::is_error)
 }
@@ -0,0 +1,133 @@
+use std::path::{Path, PathBuf};
+
+mod common;
+
+use common::run_with;
+use netl2::logic::{
+    capabilities::{Capabilities, Capability},
+    error::RuntimeError,
+    interpeter::Interpreter,
+    tokenizer::Span,
+    value::Value,
+};
+
+fn run_allowing(capabilities: Capabilities, code: &str) -> Result<Interpreter, RuntimeError> {
+    run_with(Interpreter::new().with_capabilities(capabilities), code)
+}
+
+fn granted(capabilities: &Capabilities) -> Vec<Capability> {
+    Capability::ALL
+        .into_iter()
+        .filter(|capability| capabilities.allows(*capability))
+        .collect()
+}
+
+// allowing and denying
+
+#[test]
+fn defaults_allow_everything_but_files_and_the_environment() {
+    assert_eq!(
+        granted(&Capabilities::default()),
+        [
+            Capability::Stdout,
+            Capability::Stdin,
+            Capability::Clock,
+            Capability::Random
+        ]
+    );
+    assert!(granted(&Capabilities::none()).is_empty());
+    assert_eq!(granted(&Capabilities::all()), Capability::ALL);
+}
+
+#[test]
+fn allow_and_deny_change_one_capability() {
+    let capabilities = Capabilities::default()
+        .allow(Capability::Env)
+        .deny(Capability::Stdout);
+
+    assert_eq!(
+        granted(&capabilities),
+        [
+            Capability::Stdin,
+            Capability::Env,
+            Capability::Clock,
+            Capability::Random
+        ]
+    );
+    assert_eq!(capabilities.clone().deny(Capability::FsRead), capabilities);
+}
+
+#[test]
+fn denying_file_access_forgets_its_directories() {
+    let directory = PathBuf::from(".");
+    let capabilities = Capabilities::none().allow_paths(Capability::FsRead, vec![directory]);
+    assert!(capabilities.allows_path(Capability::FsRead, Path::new("./file.txt")));
+    assert!(!capabilities.allows_path(Capability::FsWrite, Path::new("./file.txt")));
+
+    let capabilities = capabilities.deny(Capability::FsRead);
+    assert!(!capabilities.allows_path(Capability::FsRead, Path::new("./file.txt")));
+
+    // allowing again grants access everywhere, not only in the old directory
+    let capabilities = capabilities.allow(Capability::FsRead);
+    assert!(capabilities.allows_path(Capability::FsRead, Path::new("/file.txt")));
+}
+
+// missing capabilities
+
+#[test]
+fn built_ins_need_their_capability() {
+    for (code, subject, capability, span) in [
+        ("p(1)", "p", Capability::Stdout, Span::new(1, 1)),
+        (
+            "v now = clock()",
+            "clock",
+            Capability::Clock,
+            Span::new(1, 14),
+        ),
+        (
+            "v n = random(6)",
+            "random",
+            Capability::Random,
+            Span::new(1, 13),
+        ),
+        (
+            "v home = env(\"HOME\")",
+            "env",
+            Capability::Env,
+            Span::new(1, 13),
+        ),
+    ] {
+        let error = run_allowing(Capabilities::none(), code)
+            .err()
+            .expect("script should fail");
+        assert_eq!(
+            error,
+            RuntimeError::PermissionDenied(subject.to_string(), capability, span),
+            "{:?}",
+            code
+        );
+        assert_eq!(
+            error.message(),
+            format!(
+                "Permission denied, {} needs the {} capability",
+                subject, capability
+            )
+        );
+    }
+
+    assert!(run_allowing(Capabilities::all(), "v now = clock()\nv n = random(6)").is_ok());
+}
+
+#[test]
+fn missing_capabilities_can_be_caught() {
+    let interpreter = run_allowing(
+        Capabilities::default(),
+        "v kind = \"\"\nt {\n    v home = env(\"HOME\")\n} h error {\n    kind = error_kind(error)\n}",
+    )
+    .unwrap();
+
+    assert_eq!(
+        interpreter.global("kind"),
+        Some(Value::String("permission_denied".to_string()))
+    );
+}
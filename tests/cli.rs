@@ -0,0 +1,179 @@
+use std::{fs, path::PathBuf, process::Command};
+
+struct Output {
+    status: Option<i32>,
+    stdout: String,
+}
+
+// runs the script with the netl2 binary, `flags` going before the script path and
+// `arguments` after it
+fn netl2(name: &str, script: &str, flags: &[&str], arguments: &[&str]) -> Output {
+    let path = script_path(name);
+    fs::write(&path, script).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_netl2"))
+        .args(flags)
+        .arg(&path)
+        .args(arguments)
+        .env("NETL2_TEST_VALUE", "from the environment")
+        .env_remove("NETL2_TEST_MISSING")
+        .output()
+        .unwrap();
+    let _ = fs::remove_file(&path);
+
+    Output {
+        status: output.status.code(),
+        stdout: String::from_utf8(output.stdout).unwrap(),
+    }
+}
+
+fn script_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("netl2-cli-{}-{}.nl", name, std::process::id()))
+}
+
+// arguments and environment
+
+#[test]
+fn arguments_after_the_script_go_to_the_script() {
+    let output = netl2(
+        "args",
+        "p(args())\np(len(args()))",
+        &["--max-depth=50"],
+        &["one", "--two", "3"],
+    );
+
+    assert_eq!(output.stdout, "[\"one\", \"--two\", \"3\"]\n3\n");
+    assert_eq!(output.status, Some(0));
+}
+
+#[test]
+fn environment_variables_need_the_env_capability() {
+    let script = "p(env(\"NETL2_TEST_VALUE\"))\np(error_kind(env(\"NETL2_TEST_MISSING\")))";
+
+    let output = netl2("env", script, &["--allow-env"], &[]);
+    assert_eq!(output.stdout, "from the environment\nnot_found\n");
+
+    let output = netl2("env-denied", script, &[], &[]);
+    assert_eq!(
+        output.stdout,
+        "Error: Permission denied, env needs the env capability at 1:6\n"
+    );
+    assert_eq!(output.status, Some(1));
+}
+
+// capability flags
+
+#[test]
+fn capability_flags_allow_and_deny_in_order() {
+    let script = "p(env(\"NETL2_TEST_VALUE\"))";
+
+    let output = netl2("allow-all", script, &["--allow-all"], &[]);
+    assert_eq!(output.stdout, "from the environment\n");
+
+    let output = netl2(
+        "deny-after-all",
+        script,
+        &["--allow-all", "--deny-env"],
+        &[],
+    );
+    assert_eq!(
+        output.stdout,
+        "Error: Permission denied, env needs the env capability at 1:6\n"
+    );
+
+    let output = netl2(
+        "all-after-deny",
+        script,
+        &["--deny-env", "--allow-all"],
+        &[],
+    );
+    assert_eq!(output.stdout, "from the environment\n");
+
+    let output = netl2("deny-stdout", "p(1)", &["--deny-stdout"], &[]);
+    assert_eq!(
+        output.stdout,
+        "Error: Permission denied, p needs the stdout capability at 1:1\n"
+    );
+    assert_eq!(output.status, Some(1));
+}
+
+#[test]
+fn file_flags_take_directories() {
+    let path = script_path("read-self");
+    let script = format!("p(len(read_file(\"{}\")) > 0)", path.display());
+    let directory = format!("--allow-read={}", std::env::temp_dir().display());
+
+    let output = netl2("read-self", &script, &[&directory], &[]);
+    assert_eq!(output.stdout, "true\n");
+
+    let output = netl2("read-self", &script, &["--allow-read=/netl2-missing"], &[]);
+    assert!(output.stdout.starts_with("Error: Permission denied"));
+    assert_eq!(output.status, Some(1));
+}
+
+#[test]
+fn unknown_capabilities_and_invalid_flags_stop_before_running() {
+    for (flag, message) in [
+        ("--allow-network", "Error: Unknown capability network\n"),
+        ("--deny-fs", "Error: Unknown capability fs\n"),
+        ("--deny-read=/tmp", "Error: Invalid flag --deny-read=/tmp\n"),
+        ("--allow-env=/tmp", "Error: Invalid flag --allow-env=/tmp\n"),
+        ("--allow-read=", "Error: Invalid flag --allow-read=\n"),
+    ] {
+        let output = netl2("bad-flag", "p(\"ran\")", &[flag], &[]);
+        assert_eq!(output.stdout, message, "{}", flag);
+        assert_eq!(output.status, Some(1), "{}", flag);
+    }
+}
+
+// exit status
+
+#[test]
+fn exit_stops_the_script_with_its_code() {
+    let output = netl2("exit", "p(\"before\")\nexit(3)\np(\"after\")", &[], &[]);
+
+    assert_eq!(output.stdout, "before\n");
+    assert_eq!(output.status, Some(3));
+}
+
+#[test]
+fn set_exit_code_lets_the_script_finish() {
+    let output = netl2(
+        "set-exit-code",
+        "set_exit_code(4)\np(\"still running\")",
+        &[],
+        &[],
+    );
+
+    assert_eq!(output.stdout, "still running\n");
+    assert_eq!(output.status, Some(4));
+}
+
+#[test]
+fn failing_scripts_exit_with_one() {
+    for (name, script) in [
+        ("runtime-error", "p(1 / 0)"),
+        ("assertion", "assert(1 > 2)"),
+        ("undeclared", "p(missing)"),
+        ("parse-error", "v = 1"),
+        ("lex-error", "v x = 99999999999"),
+    ] {
+        let output = netl2(name, script, &[], &[]);
+        assert_eq!(
+            output.status,
+            Some(1),
+            "{} exited with {:?}",
+            name,
+            output.status
+        );
+    }
+}
+
+#[test]
+fn missing_scripts_exit_with_one() {
+    let output = Command::new(env!("CARGO_BIN_EXE_netl2"))
+        .arg(script_path("missing"))
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+}
@@ -0,0 +1,38 @@
+// Helpers shared by the integration tests. Every test file is a crate of its own and uses
+// only some of them.
+#![allow(dead_code)]
+
+use netl2::logic::{
+    ast::{parse, ASTNode},
+    error::RuntimeError,
+    interpeter::Interpreter,
+    tokenizer::tokenize,
+    typechecker::check,
+    value::Value,
+};
+
+pub fn parse_source(code: &str) -> ASTNode {
+    parse(tokenize(code).expect("script should tokenize")).expect("script should parse")
+}
+
+pub fn run_with(mut interpreter: Interpreter, code: &str) -> Result<Interpreter, RuntimeError> {
+    interpreter.run(&parse_source(code))?;
+    Ok(interpreter)
+}
+
+pub fn run(code: &str) -> Result<Interpreter, RuntimeError> {
+    run_with(Interpreter::new(), code)
+}
+
+pub fn global(interpreter: &Interpreter, name: &str) -> Value {
+    interpreter
+        .global(name)
+        .unwrap_or_else(|| panic!("{} is not declared", name))
+}
+
+pub fn type_errors(code: &str) -> Vec<String> {
+    check(&parse_source(code))
+        .into_iter()
+        .map(|diagnostic| diagnostic.message)
+        .collect()
+}
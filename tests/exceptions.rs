@@ -0,0 +1,148 @@
+mod common;
+
+use common::{global, parse_source, run, run_with};
+use netl2::logic::{
+    error::RuntimeError,
+    interpeter::Interpreter,
+    limits::Limits,
+    tokenizer::Span,
+    value::{ErrorValue, Value},
+};
+
+fn error(kind: &str, message: &str) -> Value {
+    Value::error(kind, message.to_string())
+}
+
+// catching
+
+#[test]
+fn catches_a_failed_conversion() {
+    let interpreter =
+        run("v number = 0\nt {\n    number = int(\"abc\")\n} h err {\n    v caught = err\n}")
+            .unwrap();
+
+    assert_eq!(global(&interpreter, "number"), Value::Number(0));
+    assert_eq!(
+        global(&interpreter, "caught"),
+        error("invalid_value", "Cannot convert \"abc\" to int")
+    );
+}
+
+#[test]
+fn skips_the_handler_without_an_error() {
+    let interpreter =
+        run("v ran = false\nt {\n    v n = int(\" 42 \")\n} h err {\n    ran = true\n}").unwrap();
+
+    assert_eq!(global(&interpreter, "n"), Value::Number(42));
+    assert_eq!(global(&interpreter, "ran"), Value::Boolean(false));
+}
+
+#[test]
+fn catches_errors_raised_inside_calls() {
+    let interpreter = run(
+        "f check(n) {\n    i n < 0 {\n        e error(\"negative\", \"n is \" + str(n))\n    }\n    r n\n}\nv kind = \"\"\nt {\n    check(-1)\n} h err {\n    kind = error_kind(err)\n    v message = error_message(err)\n}",
+    )
+    .unwrap();
+
+    assert_eq!(
+        global(&interpreter, "kind"),
+        Value::String("negative".to_string())
+    );
+    assert_eq!(
+        global(&interpreter, "message"),
+        Value::String("n is -1".to_string())
+    );
+}
+
+#[test]
+fn returns_from_a_handler() {
+    let interpreter = run(
+        "f parse(text) {\n    t {\n        r int(text)\n    } h err {\n        r -1\n    }\n}\nv good = parse(\"7\")\nv bad = parse(\"seven\")",
+    )
+    .unwrap();
+
+    assert_eq!(global(&interpreter, "good"), Value::Number(7));
+    assert_eq!(global(&interpreter, "bad"), Value::Number(-1));
+}
+
+#[test]
+fn state_is_restored_after_a_stack_overflow() {
+    let interpreter = run(
+        "f down(n) {\n    r down(n + 1)\n}\nv level = 0\nt {\n    down(0)\n} h err {\n    v kind = error_kind(err)\n}\nf depth(n) {\n    i n == 0 {\n        r 0\n    }\n    r depth(n - 1) + 1\n}\nlevel = depth(900)",
+    )
+    .unwrap();
+
+    assert_eq!(
+        global(&interpreter, "kind"),
+        Value::String("stack_overflow".to_string())
+    );
+    // the calls of the failed recursion are gone, so recursing close to the limit works again
+    assert_eq!(global(&interpreter, "level"), Value::Number(900));
+}
+
+// raising
+
+#[test]
+fn rethrows_a_caught_error() {
+    let error = run("t {\n    v x = 1 / 0\n} h err {\n    e err\n}")
+        .err()
+        .expect("script should fail");
+
+    assert_eq!(
+        error,
+        RuntimeError::Raised(
+            ErrorValue {
+                kind: "division_by_zero".to_string(),
+                message: "Division by zero".to_string(),
+            },
+            Span::new(4, 5)
+        )
+    );
+}
+
+#[test]
+fn uncaught_raise_stops_the_script() {
+    let mut interpreter = Interpreter::new();
+    let error = interpreter
+        .run(&parse_source("e \"broken\"\nv after = 1"))
+        .unwrap_err();
+
+    assert_eq!(error.to_string(), "Error: broken at 1:1");
+    assert_eq!(error.kind(), "error");
+    assert_eq!(interpreter.exit_code(), 1);
+    assert!(interpreter.global("after").is_none());
+}
+
+#[test]
+fn raising_other_values_fails() {
+    let error = run("e 3").err().expect("script should fail");
+
+    assert!(matches!(error, RuntimeError::InvalidValue(..)));
+}
+
+// errors that cannot be caught
+
+#[test]
+fn exit_is_not_caught() {
+    let interpreter =
+        run("v caught = false\nt {\n    exit(4)\n} h err {\n    caught = true\n}").unwrap();
+
+    assert_eq!(interpreter.exit_code(), 4);
+    assert_eq!(global(&interpreter, "caught"), Value::Boolean(false));
+}
+
+#[test]
+fn limits_are_not_caught() {
+    let limits = Limits {
+        max_steps: Some(100),
+        ..Limits::default()
+    };
+    let error = run_with(
+        Interpreter::new().with_limits(limits),
+        "t {\n    w true {\n        v a = 1\n    }\n} h err {\n    v caught = true\n}",
+    )
+    .err()
+    .expect("script should fail");
+
+    assert!(matches!(error, RuntimeError::StepLimit(100, _)));
+}
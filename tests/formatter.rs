@@ -0,0 +1,95 @@
+use std::{fs, process::Command};
+
+use netl2::logic::formatter::format;
+
+const MESSY: &str = "// greeting\nv   x=1+2*3   // inline\nf   add(a,b){r a+b}\ni x>3{p(add(x,1))}\nw x<10 { x+=1 }\nv l=[1,2,  3]\nv sq = f(n){ r n*n }\nt { v y = int(\"a\") } h err { p(err) }\n";
+
+const FORMATTED: &str = "// greeting\nv x = 1 + 2 * 3 // inline\nf add(a, b) {\n    r a + b\n}\ni x > 3 {\n    p(add(x, 1))\n}\nw x < 10 {\n    x += 1\n}\nv l = [1, 2, 3]\nv sq = f(n) {\n    r n * n\n}\nt {\n    v y = int(\"a\")\n} h err {\n    p(err)\n}\n";
+
+fn formatted(code: &str) -> String {
+    format(code).expect("source should parse")
+}
+
+// what the script prints when run with the CLI
+fn printed(script: &str) -> String {
+    let path = std::env::temp_dir().join(format!("netl2-run-{}.nl", std::process::id()));
+    fs::write(&path, script).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_netl2"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    let _ = fs::remove_file(&path);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// layout
+
+#[test]
+fn formats_every_statement() {
+    assert_eq!(formatted(MESSY), FORMATTED);
+}
+
+#[test]
+fn keeps_only_the_parentheses_that_matter() {
+    assert_eq!(
+        formatted("v a = (1 + 2) * 3\nv b = 1 + (2 * 3)\nv c = (-a) ^ 2\nv d = a - (b - c)"),
+        "v a = (1 + 2) * 3\nv b = 1 + 2 * 3\nv c = (-a) ^ 2\nv d = a - (b - c)\n"
+    );
+}
+
+#[test]
+fn reports_parse_errors() {
+    assert!(format("v = 1").is_err());
+}
+
+// round trips
+
+#[test]
+fn formatting_is_idempotent() {
+    for path in [
+        "examples/fizzbuzz.nl",
+        "examples/hello_world.nl",
+        "examples/spec_test.nl",
+    ] {
+        let once = formatted(&fs::read_to_string(path).unwrap());
+        assert_eq!(formatted(&once), once, "{} changed on a second run", path);
+    }
+    assert_eq!(formatted(FORMATTED), FORMATTED);
+}
+
+#[test]
+fn formatted_code_runs_the_same() {
+    let script = "v total = 0\nf add(a,b){r a+b}\nw total<(2+3)*2{total=add(total,3)}\np(total)";
+    assert_eq!(printed(script), "12\n");
+    assert_eq!(printed(&formatted(script)), "12\n");
+}
+
+// fmt --check
+
+#[test]
+fn check_fails_only_for_unformatted_files() {
+    let dir = std::env::temp_dir();
+    let messy = dir.join(format!("netl2-messy-{}.nl", std::process::id()));
+    let clean = dir.join(format!("netl2-clean-{}.nl", std::process::id()));
+    fs::write(&messy, MESSY).unwrap();
+    fs::write(&clean, FORMATTED).unwrap();
+
+    let check = |path: &std::path::Path| {
+        Command::new(env!("CARGO_BIN_EXE_netl2"))
+            .args(["fmt", "--check"])
+            .arg(path)
+            .output()
+            .unwrap()
+    };
+    let messy_output = check(&messy);
+    let clean_output = check(&clean);
+    let unchanged = fs::read_to_string(&messy).unwrap();
+    let _ = fs::remove_file(&messy);
+    let _ = fs::remove_file(&clean);
+
+    assert_eq!(messy_output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&messy_output.stdout).ends_with("is not formatted\n"));
+    assert_eq!(clean_output.status.code(), Some(0));
+    // --check never writes the file
+    assert_eq!(unchanged, MESSY);
+}
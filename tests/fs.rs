@@ -0,0 +1,257 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+mod common;
+
+use common::run_with;
+use netl2::logic::{
+    capabilities::{Capabilities, Capability},
+    error::RuntimeError,
+    interpeter::Interpreter,
+    value::Value,
+};
+
+// directory of its own for every test, removed again when the test ends
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("netl2-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("temporary directory should be created");
+        Self(path)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+
+    fn file(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// runs the script with `dir` declared as the temporary directory
+fn run(dir: &TempDir, capabilities: Capabilities, code: &str) -> Result<Interpreter, RuntimeError> {
+    let code = format!("v dir = \"{}\"\n{}", dir.path().display(), code);
+    run_with(Interpreter::new().with_capabilities(capabilities), &code)
+}
+
+fn file_access() -> Capabilities {
+    Capabilities::default()
+        .allow(Capability::FsRead)
+        .allow(Capability::FsWrite)
+}
+
+fn string(interpreter: &Interpreter, name: &str) -> String {
+    match interpreter.global(name) {
+        Some(Value::String(value)) => value,
+        other => panic!("{} is not a string: {:?}", name, other),
+    }
+}
+
+fn boolean(interpreter: &Interpreter, name: &str) -> bool {
+    match interpreter.global(name) {
+        Some(Value::Boolean(value)) => value,
+        other => panic!("{} is not a boolean: {:?}", name, other),
+    }
+}
+
+fn strings(interpreter: &Interpreter, name: &str) -> Vec<String> {
+    match interpreter.global(name) {
+        Some(Value::List(values)) => values
+            .iter()
+            .map(|value| match value {
+                Value::String(value) => value.clone(),
+                other => panic!("{} contains a non-string: {:?}", name, other),
+            })
+            .collect(),
+        other => panic!("{} is not a list: {:?}", name, other),
+    }
+}
+
+// reading and writing
+
+#[test]
+fn writes_and_reads_a_file() {
+    let dir = TempDir::new("write-read");
+    let interpreter = run(
+        &dir,
+        file_access(),
+        "v path = dir + \"/notes.txt\"\nv written = write_file(path, \"hello\")\nv contents = read_file(path)",
+    )
+    .unwrap();
+
+    assert!(boolean(&interpreter, "written"));
+    assert_eq!(string(&interpreter, "contents"), "hello");
+    assert_eq!(fs::read_to_string(dir.file("notes.txt")).unwrap(), "hello");
+}
+
+#[test]
+fn appends_to_a_file_and_reads_its_lines() {
+    let dir = TempDir::new("append-lines");
+    fs::write(dir.file("log.txt"), "first\n").unwrap();
+
+    let interpreter = run(
+        &dir,
+        file_access(),
+        "v path = dir + \"/log.txt\"\nappend_file(path, \"second\")\nv lines = read_lines(path)",
+    )
+    .unwrap();
+
+    assert_eq!(strings(&interpreter, "lines"), ["first", "second"]);
+}
+
+#[test]
+fn append_creates_a_missing_file() {
+    let dir = TempDir::new("append-create");
+    run(
+        &dir,
+        file_access(),
+        "append_file(dir + \"/new.txt\", \"text\")",
+    )
+    .unwrap();
+
+    assert_eq!(fs::read_to_string(dir.file("new.txt")).unwrap(), "text");
+}
+
+// directories and deleting
+
+#[test]
+fn lists_a_directory_sorted() {
+    let dir = TempDir::new("list");
+    for name in ["b.nl", "a.nl", "c.txt"] {
+        fs::write(dir.file(name), "").unwrap();
+    }
+
+    let interpreter = run(&dir, file_access(), "v names = list_dir(dir)").unwrap();
+
+    assert_eq!(strings(&interpreter, "names"), ["a.nl", "b.nl", "c.txt"]);
+}
+
+#[test]
+fn deletes_a_file() {
+    let dir = TempDir::new("delete");
+    fs::write(dir.file("old.txt"), "").unwrap();
+
+    let interpreter = run(
+        &dir,
+        file_access(),
+        "v path = dir + \"/old.txt\"\nv before = file_exists(path)\ndelete_file(path)\nv after = file_exists(path)",
+    )
+    .unwrap();
+
+    assert!(boolean(&interpreter, "before"));
+    assert!(!boolean(&interpreter, "after"));
+    assert!(!dir.file("old.txt").exists());
+}
+
+// failures
+
+#[test]
+fn missing_file_is_an_error_value() {
+    let dir = TempDir::new("missing");
+    let interpreter = run(
+        &dir,
+        file_access(),
+        "v result = read_file(dir + \"/missing.txt\")\nv failed = is_error(result)\nv kind = error_kind(result)\nv message = error_message(result)",
+    )
+    .unwrap();
+
+    assert!(boolean(&interpreter, "failed"));
+    assert_eq!(string(&interpreter, "kind"), "not_found");
+    assert!(string(&interpreter, "message").contains("missing.txt"));
+}
+
+#[test]
+fn failed_operations_return_error_values() {
+    let dir = TempDir::new("failures");
+    let interpreter = run(
+        &dir,
+        file_access(),
+        "v deleted = delete_file(dir + \"/missing.txt\")\nv listed = list_dir(dir + \"/missing\")\nv written = write_file(dir + \"/missing/file.txt\", \"\")",
+    )
+    .unwrap();
+
+    for name in ["deleted", "listed", "written"] {
+        assert!(
+            matches!(interpreter.global(name), Some(Value::Error(error)) if error.kind == "not_found"),
+            "{} did not fail with not_found",
+            name
+        );
+    }
+}
+
+// permissions
+
+#[test]
+fn file_access_is_denied_by_default() {
+    let dir = TempDir::new("denied");
+    let error = run(
+        &dir,
+        Capabilities::default(),
+        "v x = read_file(dir + \"/a\")",
+    )
+    .err()
+    .expect("script should fail");
+
+    assert!(matches!(
+        error,
+        RuntimeError::PermissionDenied(_, Capability::FsRead, _)
+    ));
+}
+
+#[test]
+fn reading_does_not_allow_writing() {
+    let dir = TempDir::new("read-only");
+    let capabilities = Capabilities::default().allow(Capability::FsRead);
+    let error = run(&dir, capabilities, "write_file(dir + \"/a\", \"\")")
+        .err()
+        .expect("script should fail");
+
+    assert!(matches!(
+        error,
+        RuntimeError::PermissionDenied(_, Capability::FsWrite, _)
+    ));
+    assert!(!dir.file("a").exists());
+}
+
+#[test]
+fn access_can_be_limited_to_a_directory() {
+    let dir = TempDir::new("limited");
+    fs::create_dir(dir.file("allowed")).unwrap();
+    fs::write(dir.file("secret.txt"), "secret").unwrap();
+    let capabilities =
+        Capabilities::default().allow_paths(Capability::FsRead, vec![dir.file("allowed")]);
+
+    run(
+        &dir,
+        capabilities.clone(),
+        "v names = list_dir(dir + \"/allowed\")",
+    )
+    .unwrap();
+
+    for path in ["/secret.txt", "/allowed/../secret.txt"] {
+        let code = format!("v x = read_file(dir + \"{}\")", path);
+        let error = run(&dir, capabilities.clone(), &code)
+            .err()
+            .expect("script should fail");
+        assert!(
+            matches!(
+                error,
+                RuntimeError::PermissionDenied(_, Capability::FsRead, _)
+            ),
+            "{} was not denied",
+            path
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use std::rc::Rc;
+
+mod common;
+
+use common::{global, run, run_with};
+use netl2::logic::{
+    error::{Frame, RuntimeError},
+    interpeter::Interpreter,
+    limits::Limits,
+    tokenizer::Span,
+    value::Value,
+};
+
+// returns
+
+#[test]
+fn return_unwinds_nested_blocks() {
+    let interpreter = run(
+        "f find(limit) {\n    v n = 0\n    w true {\n        i n == limit {\n            w true {\n                r n * 10\n            }\n        }\n        n += 1\n    }\n}\nv found = find(3)\nv after = 0\nafter = find(0) + 1",
+    )
+    .unwrap();
+
+    assert_eq!(global(&interpreter, "found"), Value::Number(30));
+    assert_eq!(global(&interpreter, "after"), Value::Number(1));
+}
+
+#[test]
+fn return_leaves_only_the_innermost_call() {
+    let interpreter = run(
+        "f inner {\n    i true {\n        r 1\n    }\n    r 2\n}\nf outer {\n    v value = inner()\n    r value + 10\n}\nv result = outer()",
+    )
+    .unwrap();
+
+    assert_eq!(global(&interpreter, "result"), Value::Number(11));
+}
+
+#[test]
+fn top_level_return_stops_the_script() {
+    let interpreter = run("v a = 1\ni a == 1 {\n    r\n}\na = 2\nv b = 3").unwrap();
+
+    assert_eq!(global(&interpreter, "a"), Value::Number(1));
+    assert!(interpreter.global("b").is_none());
+    assert_eq!(interpreter.exit_code(), 0);
+}
+
+// logical operators
+
+#[test]
+fn logical_operators_skip_the_right_side_when_decided() {
+    let interpreter = run(
+        "v calls = 0\nf touch {\n    calls += 1\n    r true\n}\nv and_false = false && touch()\nv or_true = true || touch()\nv skipped = false && 1 / 0 == 1\nv and_true = true && touch()\nv or_false = false || touch()",
+    )
+    .unwrap();
+
+    assert_eq!(global(&interpreter, "and_false"), Value::Boolean(false));
+    assert_eq!(global(&interpreter, "or_true"), Value::Boolean(true));
+    assert_eq!(global(&interpreter, "skipped"), Value::Boolean(false));
+    assert_eq!(global(&interpreter, "and_true"), Value::Boolean(true));
+    assert_eq!(global(&interpreter, "or_false"), Value::Boolean(true));
+    assert_eq!(global(&interpreter, "calls"), Value::Number(2));
+}
+
+#[test]
+fn logical_operators_only_take_bools() {
+    for code in [
+        "v a = 1 && true",
+        "v a = true && 3",
+        "v a = false || \"yes\"",
+    ] {
+        let error = run(code).err().expect("script should fail");
+        assert!(
+            matches!(error, RuntimeError::InvalidOperands(..)),
+            "{:?} failed with {:?}",
+            code,
+            error
+        );
+    }
+}
+
+// bitwise operators
+
+#[test]
+fn bitwise_operators_work_on_all_32_bits() {
+    let interpreter = run(
+        "v and = 6 & 3\nv or = 6 | 3\nv xor = 6 ~ 3\nv not = ~5\nv left = 1 << 31\nv right = -16 >> 2",
+    )
+    .unwrap();
+
+    assert_eq!(global(&interpreter, "and"), Value::Number(2));
+    assert_eq!(global(&interpreter, "or"), Value::Number(7));
+    assert_eq!(global(&interpreter, "xor"), Value::Number(5));
+    assert_eq!(global(&interpreter, "not"), Value::Number(-6));
+    assert_eq!(global(&interpreter, "left"), Value::Number(i32::MIN));
+    assert_eq!(global(&interpreter, "right"), Value::Number(-4));
+}
+
+#[test]
+fn shifts_out_of_range_are_errors() {
+    for (code, message) in [
+        ("v s = 32\np(1 << s)", "Shift amount 32 is out of range"),
+        ("v s = -1\np(1 >> s)", "Shift amount -1 is out of range"),
+    ] {
+        let error = run(code).err().expect("script should fail");
+        assert!(
+            matches!(&error, RuntimeError::InvalidValue(text, _) if text == message),
+            "{:?} failed with {:?}",
+            code,
+            error
+        );
+    }
+}
+
+#[test]
+fn bitwise_operators_on_bools_suggest_the_logical_ones() {
+    for (code, message) in [
+        (
+            "v a = true\np(a & false)",
+            "Cannot apply & to bool and bool (use && for a logical and)",
+        ),
+        (
+            "v a = true\np(a | false)",
+            "Cannot apply | to bool and bool (use || for a logical or)",
+        ),
+        ("v a = 1\np(a & \"x\")", "Cannot apply & to int and str"),
+    ] {
+        let error = run(code).err().expect("script should fail");
+        assert_eq!(error.message(), message, "{:?}", code);
+        assert_eq!(error.span(), Span::new(2, 5));
+    }
+}
+
+// compound assignment
+
+#[test]
+fn compound_assignment_applies_every_operator() {
+    let interpreter = run(
+        "v a = 10\na += 5\na -= 3\na *= 2\na /= 4\na %= 4\nv b = 3\nb ^= 2\nv text = \"a\"\ntext += \"b\"",
+    )
+    .unwrap();
+
+    assert_eq!(global(&interpreter, "a"), Value::Number(2));
+    assert_eq!(global(&interpreter, "b"), Value::Number(9));
+    assert_eq!(
+        global(&interpreter, "text"),
+        Value::String("ab".to_string())
+    );
+}
+
+#[test]
+fn compound_assignment_updates_the_variable_where_it_was_declared() {
+    let interpreter =
+        run("v count = 0\nf bump(by) {\n    count += by\n}\nbump(2)\nbump(3)").unwrap();
+
+    assert_eq!(global(&interpreter, "count"), Value::Number(5));
+}
+
+#[test]
+fn compound_assignment_fails_like_the_operator() {
+    let error = run("missing += 1").err().expect("script should fail");
+    assert!(matches!(error, RuntimeError::UndeclaredVariable(..)));
+
+    let error = run("v a = 2147483647\na += 1")
+        .err()
+        .expect("script should fail");
+    assert!(matches!(error, RuntimeError::Overflow(..)));
+
+    let error = run("v a = 1\na /= 0").err().expect("script should fail");
+    assert!(matches!(error, RuntimeError::DivisionByZero(..)));
+}
+
+// functions as values
+
+#[test]
+fn closures_keep_their_own_environment() {
+    let interpreter = run(
+        "f counter {\n    v count = 0\n    r f {\n        count += 1\n        r count\n    }\n}\nv a = counter()\nv b = counter()\na()\na()\nv from_a = a()\nv from_b = b()",
+    )
+    .unwrap();
+
+    assert_eq!(global(&interpreter, "from_a"), Value::Number(3));
+    assert_eq!(global(&interpreter, "from_b"), Value::Number(1));
+}
+
+#[test]
+fn closures_capture_parameters() {
+    let interpreter = run(
+        "f adder(n) {\n    r f(x) {\n        r x + n\n    }\n}\nv add_two = adder(2)\nv add_ten = adder(10)\nv result = add_two(1) + add_ten(1)",
+    )
+    .unwrap();
+
+    assert_eq!(global(&interpreter, "result"), Value::Number(14));
+}
+
+#[test]
+fn functions_are_passed_as_values() {
+    let interpreter = run(
+        "f double(x) {\n    r x * 2\n}\nv twice = double\nv doubled = map([1, 2, 3], twice)\nv even = filter(range(6), f(x) {\n    r x % 2 == 0\n})\nv sum = fold(doubled, 0, f(total, x) {\n    r total + x\n})",
+    )
+    .unwrap();
+
+    let numbers =
+        |values: &[i32]| Value::List(Rc::new(values.iter().copied().map(Value::Number).collect()));
+    assert_eq!(global(&interpreter, "doubled"), numbers(&[2, 4, 6]));
+    assert_eq!(global(&interpreter, "even"), numbers(&[0, 2, 4]));
+    assert_eq!(global(&interpreter, "sum"), Value::Number(12));
+}
+
+#[test]
+fn only_functions_are_callable() {
+    let error = run("v number = 3\nnumber()")
+        .err()
+        .expect("script should fail");
+    assert!(matches!(error, RuntimeError::NotCallable(..)));
+
+    let error = run("v id = f(x) {\n    r x\n}\nid(1, 2)")
+        .err()
+        .expect("script should fail");
+    assert!(matches!(error, RuntimeError::WrongArgumentCount(..)));
+}
+
+// recursion
+
+#[test]
+fn recursive_calls_get_frames_of_their_own() {
+    let interpreter = run(
+        "f factorial(n) {\n    i n <= 1 {\n        r 1\n    }\n    r n * factorial(n - 1)\n}\nf fib(n) {\n    i n < 2 {\n        r n\n    }\n    r fib(n - 1) + fib(n - 2)\n}\nv n = 3\nv fact = factorial(10)\nv fibonacci = fib(15)",
+    )
+    .unwrap();
+
+    assert_eq!(global(&interpreter, "fact"), Value::Number(3628800));
+    assert_eq!(global(&interpreter, "fibonacci"), Value::Number(610));
+    assert_eq!(global(&interpreter, "n"), Value::Number(3));
+}
+
+#[test]
+fn stack_overflow_reports_the_call_chain() {
+    let limits = Limits {
+        max_depth: 4,
+        ..Limits::default()
+    };
+    let error = run_with(
+        Interpreter::new().with_limits(limits),
+        "f ping(n) {\n    r pong(n + 1)\n}\nf pong(n) {\n    r ping(n + 1)\n}\nf start {\n    r ping(0)\n}\nstart()",
+    )
+    .err()
+    .expect("script should fail");
+
+    let frame = |function: &str, line, column| Frame {
+        function: function.to_string(),
+        span: Span::new(line, column),
+    };
+    assert_eq!(
+        error,
+        RuntimeError::StackOverflow(vec![
+            frame("start", 10, 6),
+            frame("ping", 8, 11),
+            frame("pong", 2, 11),
+            frame("ping", 5, 11),
+            frame("pong", 2, 11),
+        ])
+    );
+    assert_eq!(
+        error.to_string(),
+        "Error: Stack overflow, more than 4 nested function calls at 2:11\n  in pong called at 2:11\n  in ping called at 5:11\n  in pong called at 2:11\n  in ping called at 8:11\n  in start called at 10:6"
+    );
+}
+
+#[test]
+fn stack_overflow_folds_repeated_calls() {
+    let limits = Limits {
+        max_depth: 50,
+        ..Limits::default()
+    };
+    let error = run_with(
+        Interpreter::new().with_limits(limits),
+        "f down(n) {\n    r down(n + 1)\n}\ndown(0)",
+    )
+    .err()
+    .expect("script should fail");
+
+    assert_eq!(
+        error.to_string(),
+        "Error: Stack overflow, more than 50 nested function calls at 2:11\n  in down called at 2:11 (50 times)\n  in down called at 4:5"
+    );
+}
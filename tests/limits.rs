@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+mod common;
+
+use common::{global, parse_source, run_with};
+use netl2::logic::{error::RuntimeError, interpeter::Interpreter, limits::Limits, value::Value};
+
+fn run_limited(limits: Limits, code: &str) -> Result<Interpreter, RuntimeError> {
+    run_with(Interpreter::new().with_limits(limits), code)
+}
+
+// steps
+
+#[test]
+fn step_limit_stops_endless_loops() {
+    let limits = Limits {
+        max_steps: Some(1000),
+        ..Limits::default()
+    };
+    let error = run_limited(limits, "w true {\n}")
+        .err()
+        .expect("script should fail");
+
+    assert!(matches!(error, RuntimeError::StepLimit(1000, _)));
+    assert_eq!(
+        error.to_string(),
+        "Error: Step limit of 1000 exceeded at 1:1"
+    );
+}
+
+#[test]
+fn step_limit_counts_statements_and_iterations() {
+    let limits = Limits {
+        max_steps: Some(17),
+        ..Limits::default()
+    };
+    // the declaration, the loop, and 5 iterations counting once each and once per statement
+    let interpreter =
+        run_limited(limits, "v n = 0\nw n < 5 {\n    n += 1\n    v x = n\n}").unwrap();
+    assert_eq!(global(&interpreter, "n"), Value::Number(5));
+
+    let error = run_limited(limits, "v n = 0\nw n < 6 {\n    n += 1\n    v x = n\n}")
+        .err()
+        .expect("script should fail");
+    assert!(matches!(error, RuntimeError::StepLimit(17, _)));
+}
+
+#[test]
+fn every_run_gets_the_full_budget() {
+    let limits = Limits {
+        max_steps: Some(3),
+        ..Limits::default()
+    };
+    let mut interpreter = run_limited(limits, "v a = 1\nv b = 2").unwrap();
+    let ast = parse_source("v c = 3\nv d = 4");
+
+    assert!(interpreter.run(&ast).is_ok());
+}
+
+// time
+
+#[test]
+fn timeout_stops_long_scripts() {
+    let limits = Limits {
+        timeout: Some(Duration::from_millis(50)),
+        ..Limits::default()
+    };
+    let error = run_limited(limits, "w true {\n}")
+        .err()
+        .expect("script should fail");
+
+    assert!(matches!(error, RuntimeError::Timeout(..)));
+    assert_eq!(
+        error.to_string(),
+        "Error: Time limit of 50ms exceeded at 1:1"
+    );
+}
+
+// memory
+
+#[test]
+fn memory_limit_stops_large_values() {
+    let limits = Limits {
+        max_memory: Some(100),
+        ..Limits::default()
+    };
+    let error = run_limited(limits, "v text = \"x\"\nw true {\n    text += text\n}")
+        .err()
+        .expect("script should fail");
+
+    assert!(matches!(error, RuntimeError::MemoryLimit(100, _)));
+    assert_eq!(
+        error.to_string(),
+        "Error: Memory limit of 100 bytes exceeded at 3:5"
+    );
+}
+
+#[test]
+fn memory_limit_stops_large_lists() {
+    let limits = Limits {
+        max_memory: Some(1000),
+        ..Limits::default()
+    };
+    let error = run_limited(limits, "v numbers = range(1000000)")
+        .err()
+        .expect("script should fail");
+
+    assert!(matches!(error, RuntimeError::MemoryLimit(1000, _)));
+}
+
+#[test]
+fn memory_limit_allows_many_small_values() {
+    let limits = Limits {
+        max_memory: Some(1000),
+        ..Limits::default()
+    };
+    // only one short line is kept at a time, however many are created
+    let interpreter = run_limited(
+        limits,
+        "v n = 0\nv line = \"\"\nw n < 100000 {\n    line = \"line \" + str(n)\n    n += 1\n}",
+    )
+    .unwrap();
+
+    assert_eq!(
+        global(&interpreter, "line"),
+        Value::String("line 99999".to_string())
+    );
+}
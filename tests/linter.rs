@@ -0,0 +1,158 @@
+use netl2::logic::linter::{
+    lint, INFINITE_LOOP, NAMING, SELF_ASSIGNMENT, UNREACHABLE_CODE, UNUSED_FUNCTION,
+    UNUSED_VARIABLE,
+};
+
+// the rule codes reported for the code, in order
+fn lint_codes(code: &str) -> Vec<&'static str> {
+    lint(code)
+        .expect("source should parse")
+        .into_iter()
+        .filter_map(|diagnostic| diagnostic.code)
+        .collect()
+}
+
+// the diagnostics as printed by the CLI
+fn lint_messages(code: &str) -> Vec<String> {
+    lint(code)
+        .expect("source should parse")
+        .into_iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect()
+}
+
+// rules
+
+#[test]
+fn clean_code_has_no_warnings() {
+    assert!(lint_codes(
+        "f double(n) {\n    r n * 2\n}\nv total = double(2)\nw total < 10 {\n    total += 1\n}\np(total)"
+    )
+    .is_empty());
+}
+
+#[test]
+fn reports_names_not_in_lower_snake_case() {
+    assert_eq!(
+        lint_messages("v myValue = 1\np(myValue)\nf DoIt {}\nDoIt()"),
+        [
+            "Warning[naming]: Variable myValue should be written in lower snake case at 1:3",
+            "Warning[naming]: Function DoIt should be written in lower snake case at 3:3",
+        ]
+    );
+}
+
+#[test]
+fn reports_unused_variables_and_functions() {
+    assert_eq!(
+        lint_messages("v unused = 1\nv _ignored = 2\nf helper {}\nf test_it {}\nf _spare {}"),
+        [
+            "Warning[unused-variable]: Variable unused is never read at 1:3",
+            "Warning[unused-function]: Function helper is never called at 3:3",
+        ]
+    );
+    // passing a function around counts as using it
+    assert!(lint_codes("f helper {}\nv callback = helper\np(callback)").is_empty());
+}
+
+#[test]
+fn reports_code_after_return_and_raise() {
+    assert_eq!(
+        lint_messages("f g {\n    r 1\n    p(2)\n}\ng()\ne \"stop\"\np(3)"),
+        [
+            "Warning[unreachable-code]: Code after r is never executed at 3:5",
+            "Warning[unreachable-code]: Code after e is never executed at 7:1",
+        ]
+    );
+}
+
+#[test]
+fn reports_code_after_exit() {
+    assert_eq!(
+        lint_messages("p(1)\nexit(0)\nv after = 2\np(after)"),
+        ["Warning[unreachable-code]: Code after exit is never executed at 3:3"]
+    );
+    assert_eq!(
+        lint_messages("i true {\n    exit(2)\n    print_it(3)\n}\nf print_it(n) {\n    p(n)\n}"),
+        ["Warning[unreachable-code]: Code after exit is never executed at 3:13"]
+    );
+}
+
+#[test]
+fn reports_self_assignment() {
+    assert_eq!(
+        lint_messages("v a = 1\na = a\np(a)"),
+        ["Warning[self-assignment]: a is assigned to itself at 2:1"]
+    );
+}
+
+#[test]
+fn every_rule_has_its_code() {
+    assert_eq!(
+        lint_codes("v Unused = 1\nf helper {\n    r\n    p(1)\n}\nv a = 2\na = a\np(a)\nw true {}"),
+        [
+            NAMING,
+            UNUSED_VARIABLE,
+            UNUSED_FUNCTION,
+            UNREACHABLE_CODE,
+            SELF_ASSIGNMENT,
+            INFINITE_LOOP
+        ]
+    );
+}
+
+// suppression
+
+#[test]
+fn allow_comments_suppress_their_line_and_the_next() {
+    assert!(lint_codes(
+        "v unused = 1 // lint:allow(unused-variable)\n// lint:allow(naming, unused-function)\nf DoIt {}"
+    )
+    .is_empty());
+    assert!(lint_codes("// lint:allow(all)\nv Unused = 1").is_empty());
+}
+
+#[test]
+fn allow_comments_only_suppress_the_named_rules() {
+    assert_eq!(
+        lint_codes("// lint:allow(naming)\nv unused = 1\nv other = 2"),
+        [UNUSED_VARIABLE, UNUSED_VARIABLE]
+    );
+    // a comment after code does not reach the next line
+    assert_eq!(
+        lint_codes("p(1) // lint:allow(unused-variable)\nv unused = 1"),
+        [UNUSED_VARIABLE]
+    );
+}
+
+// infinite loops
+
+#[test]
+fn loops_left_by_a_return_or_raise_are_finite() {
+    assert!(lint_codes("f run {\n    w true {\n        r 1\n    }\n}\nrun()").is_empty());
+    assert!(lint_codes("w true {\n    i true {\n        e \"stop\"\n    }\n}").is_empty());
+}
+
+#[test]
+fn raises_caught_inside_the_loop_do_not_leave_it() {
+    assert_eq!(
+        lint_codes(
+            "w true {\n    t {\n        e \"again\"\n    } h err {\n        p(err)\n    }\n}"
+        ),
+        [INFINITE_LOOP]
+    );
+    assert!(lint_codes(
+        "w true {\n    t {\n        e \"again\"\n    } h err {\n        e err\n    }\n}"
+    )
+    .is_empty());
+}
+
+#[test]
+fn loops_left_by_exit_are_finite() {
+    assert!(lint_codes("w true {\n    exit(0)\n}").is_empty());
+    // exit cannot be caught, so it leaves the loop even inside t
+    assert!(lint_codes(
+        "w true {\n    t {\n        exit(1)\n    } h err {\n        p(err)\n    }\n}"
+    )
+    .is_empty());
+}
@@ -0,0 +1,219 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+};
+
+use serde_json::{json, Value};
+
+const URI: &str = "file:///test.nl";
+
+// drives netl2-lsp over stdio like an editor would
+struct Client {
+    server: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut server = Command::new(env!("CARGO_BIN_EXE_netl2-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("server should start");
+        let stdin = server.stdin.take().unwrap();
+        let stdout = BufReader::new(server.stdout.take().unwrap());
+
+        let mut client = Self {
+            server,
+            stdin,
+            stdout,
+            next_id: 0,
+        };
+        let result = client.request("initialize", json!({ "capabilities": {} }));
+        assert!(result["capabilities"]["completionProvider"].is_object());
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = None;
+        loop {
+            let mut line = String::new();
+            let read = self.stdout.read_line(&mut line).unwrap();
+            assert!(read > 0, "server closed the connection");
+
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Content-Length: ") {
+                length = Some(value.parse().unwrap());
+            }
+        }
+
+        let mut body = vec![0; length.expect("message should have a length")];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    // skips notifications sent before the response
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+
+        loop {
+            let message = self.receive();
+            if message["id"] == id {
+                return message["result"].clone();
+            }
+        }
+    }
+
+    // opens the document and returns the diagnostics published for it
+    fn open(&mut self, text: &str) -> Vec<Value> {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": URI, "languageId": "netl2", "version": 1, "text": text }
+            }),
+        );
+
+        let message = self.receive();
+        assert_eq!(message["method"], "textDocument/publishDiagnostics");
+        message["params"]["diagnostics"].as_array().unwrap().clone()
+    }
+
+    fn at(&mut self, method: &str, line: u32, character: u32) -> Value {
+        self.request(
+            method,
+            json!({
+                "textDocument": { "uri": URI },
+                "position": { "line": line, "character": character }
+            }),
+        )
+    }
+
+    fn completions(&mut self, line: u32, character: u32) -> Vec<String> {
+        self.at("textDocument/completion", line, character)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["label"].as_str().unwrap().to_string())
+            .collect()
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            self.request("shutdown", Value::Null);
+            self.notify("exit", Value::Null);
+        }
+        let _ = self.server.kill();
+        let _ = self.server.wait();
+    }
+}
+
+#[test]
+fn completion_offers_the_names_in_scope() {
+    let mut client = Client::start();
+    let diagnostics = client.open(
+        "v top = 1\nf one(param) {\n    v secret = 2\n    p(param + secret)\n}\nf two(other) {\n    v mine = 3\n    p(other + mine)\n}\n",
+    );
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+    let inside_two = client.completions(7, 4);
+    for name in ["top", "one", "two", "other", "mine", "v"] {
+        assert!(
+            inside_two.iter().any(|label| label == name),
+            "{} missing",
+            name
+        );
+    }
+    for name in ["secret", "param"] {
+        assert!(
+            !inside_two.iter().any(|label| label == name),
+            "{} offered",
+            name
+        );
+    }
+
+    let inside_one = client.completions(3, 4);
+    for name in ["top", "param", "secret"] {
+        assert!(
+            inside_one.iter().any(|label| label == name),
+            "{} missing",
+            name
+        );
+    }
+    for name in ["two", "other", "mine"] {
+        assert!(
+            !inside_one.iter().any(|label| label == name),
+            "{} offered",
+            name
+        );
+    }
+
+    let top_level = client.completions(9, 0);
+    assert!(top_level.iter().any(|label| label == "two"));
+    assert!(!top_level.iter().any(|label| label == "mine"));
+}
+
+#[test]
+fn completion_counts_utf16_code_units() {
+    let mut client = Client::start();
+    let diagnostics = client.open("f greet(name) {\n    p(\"😀😀😀😀\" + name) }\ngreet(\"x\")\n");
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+    // each emoji is two code units, so the } is at 25 and not at its character index 21
+    let before_brace = client.completions(1, 25);
+    assert!(before_brace.iter().any(|label| label == "name"));
+
+    let after_brace = client.completions(1, 26);
+    assert!(!after_brace.iter().any(|label| label == "name"));
+}
+
+#[test]
+fn definition_and_hover_find_the_declaration() {
+    let mut client = Client::start();
+    client.open("// Says hello.\nf greet(name) {\n    p(\"Hello \" + name)\n}\ngreet(\"Ann\")\n");
+
+    let definition = client.at("textDocument/definition", 4, 2);
+    assert_eq!(definition["uri"], URI);
+    assert_eq!(
+        definition["range"],
+        json!({ "start": { "line": 1, "character": 2 }, "end": { "line": 1, "character": 7 } })
+    );
+
+    let hover = client.at("textDocument/hover", 4, 2);
+    assert_eq!(
+        hover["contents"]["value"],
+        "```netl2\nf greet(name)\n```\n\nSays hello."
+    );
+}
+
+#[test]
+fn lex_errors_are_reported_without_stopping_the_server() {
+    let mut client = Client::start();
+    let diagnostics = client.open("v x = 99999999999\n");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("Unexpected character '9'"));
+    assert!(client.completions(0, 0).iter().any(|label| label == "v"));
+}
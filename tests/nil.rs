@@ -0,0 +1,93 @@
+mod common;
+
+use common::{global, run, type_errors};
+use netl2::logic::{error::RuntimeError, value::Value};
+
+// values
+
+#[test]
+fn functions_without_a_value_return_nil() {
+    let interpreter =
+        run("f nothing {}\nf early {\n    r\n}\nv a = nothing()\nv b = early()").unwrap();
+
+    assert_eq!(global(&interpreter, "a"), Value::Nil);
+    assert_eq!(global(&interpreter, "b"), Value::Nil);
+}
+
+#[test]
+fn nil_only_equals_nil() {
+    let interpreter = run(
+        "v same = nil == nil\nv number = 0 == nil\nv text = \"\" != nil\nv flag = false == nil",
+    )
+    .unwrap();
+
+    assert_eq!(global(&interpreter, "same"), Value::Boolean(true));
+    assert_eq!(global(&interpreter, "number"), Value::Boolean(false));
+    assert_eq!(global(&interpreter, "text"), Value::Boolean(true));
+    assert_eq!(global(&interpreter, "flag"), Value::Boolean(false));
+}
+
+#[test]
+fn nil_is_false_in_conditions_and_prints_as_nil() {
+    let interpreter =
+        run("v ran = false\ni nil {\n    ran = true\n}\nv text = str(nil)\nv list = str([nil, 1])")
+            .unwrap();
+
+    assert_eq!(global(&interpreter, "ran"), Value::Boolean(false));
+    assert_eq!(
+        global(&interpreter, "text"),
+        Value::String("nil".to_string())
+    );
+    assert_eq!(
+        global(&interpreter, "list"),
+        Value::String("[nil, 1]".to_string())
+    );
+}
+
+#[test]
+fn nil_is_not_a_number() {
+    let error = run("v x = nil + 1").err().expect("script should fail");
+
+    assert!(matches!(error, RuntimeError::InvalidOperands(..)));
+}
+
+// null coalescing
+
+#[test]
+fn coalescing_uses_the_default_only_for_nil() {
+    let interpreter = run(
+        "f nothing {}\nv missing = nothing() ?? 5\nv present = 0 ?? 5\nv chained = nil ?? nil ?? \"last\"",
+    )
+    .unwrap();
+
+    assert_eq!(global(&interpreter, "missing"), Value::Number(5));
+    assert_eq!(global(&interpreter, "present"), Value::Number(0));
+    assert_eq!(
+        global(&interpreter, "chained"),
+        Value::String("last".to_string())
+    );
+}
+
+#[test]
+fn coalescing_skips_the_default_when_not_needed() {
+    let interpreter =
+        run("v calls = 0\nf fallback {\n    calls += 1\n    r 1\n}\nv value = 2 ?? fallback()")
+            .unwrap();
+
+    assert_eq!(global(&interpreter, "value"), Value::Number(2));
+    assert_eq!(global(&interpreter, "calls"), Value::Number(0));
+}
+
+// types
+
+#[test]
+fn nil_checks_against_every_type() {
+    assert!(type_errors(
+        "v a: int = nil\nv b = nil\nb = \"text\"\nv c = a == nil\nv d = (b ?? \"x\") + \"y\""
+    )
+    .is_empty());
+    assert_eq!(
+        type_errors("v a = nil + 1"),
+        ["Operator + cannot be applied to nil and int"]
+    );
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use common::parse_source;
+use netl2::logic::{ast::ASTNode, optimizer::optimize};
+
+fn optimized(code: &str) -> Vec<ASTNode> {
+    match optimize(parse_source(code)) {
+        ASTNode::Program(statements) => statements,
+        other => panic!("{:?} is not a program", other),
+    }
+}
+
+// the folded value of every declaration, in order
+fn values(code: &str) -> Vec<ASTNode> {
+    optimized(code)
+        .into_iter()
+        .map(|statement| match statement {
+            ASTNode::VariableDeclaration(_, _, value, _) => *value,
+            other => panic!("{:?} is not a declaration", other),
+        })
+        .collect()
+}
+
+// constant folding
+
+#[test]
+fn folds_constant_expressions() {
+    let values = values(
+        "v a = 2 * 3 + 4\nv b = -(2 ^ 3)\nv c = \"ab\" + \"cd\"\nv d = 1 < 2 && !false\nv bits = 0xF0 | 1 << 2\nv fallback = nil ?? 3",
+    );
+
+    assert!(matches!(values[0], ASTNode::Number(10)));
+    assert!(matches!(values[1], ASTNode::Number(-8)));
+    assert!(matches!(&values[2], ASTNode::String(value) if value == "abcd"));
+    assert!(matches!(values[3], ASTNode::Boolean(true)));
+    assert!(matches!(values[4], ASTNode::Number(0xF4)));
+    assert!(matches!(values[5], ASTNode::Number(3)));
+}
+
+#[test]
+fn leaves_operations_that_fail_at_runtime() {
+    let values = values("v a = 1 / 0\nv b = 2147483647 + 1\nv c = 1 + \"a\"\nv d = 1 << 40");
+
+    for value in values {
+        assert!(
+            matches!(value, ASTNode::Binary(..)),
+            "{:?} was folded",
+            value
+        );
+    }
+}
+
+#[test]
+fn folds_only_the_constant_parts() {
+    let values = values("v x = 1\nv y = x + 2 * 3");
+
+    assert!(matches!(
+        &values[1],
+        ASTNode::Binary(left, _, right, _)
+            if matches!(left.as_ref(), ASTNode::Identifier(name, _) if name == "x")
+                && matches!(right.as_ref(), ASTNode::Number(6))
+    ));
+}
+
+#[test]
+fn folds_inside_functions_and_lambdas() {
+    let statements = optimized("f g {\n    r 1 + 1\n}\nv square = f(x) {\n    r 2 * 2\n}");
+
+    assert!(matches!(
+        &statements[0],
+        ASTNode::FunctionDeclaration(_, _, body, _)
+            if matches!(&body[..], [ASTNode::Return(Some(value), _)]
+                if matches!(value.as_ref(), ASTNode::Number(2)))
+    ));
+    assert!(matches!(
+        &statements[1],
+        ASTNode::VariableDeclaration(_, _, value, _)
+            if matches!(value.as_ref(), ASTNode::Lambda(_, body, _)
+                if matches!(&body[..], [ASTNode::Return(Some(value), _)]
+                    if matches!(value.as_ref(), ASTNode::Number(4))))
+    ));
+}
+
+// dead code
+
+#[test]
+fn removes_branches_decided_by_constants() {
+    let statements = optimized(
+        "i 1 > 2 {\n    p(1)\n}\ni true {\n    v inlined = 1\n}\nw false {\n    p(2)\n}\nt {\n} h err {\n    p(err)\n}",
+    );
+
+    assert_eq!(statements.len(), 1);
+    assert!(matches!(
+        &statements[0],
+        ASTNode::VariableDeclaration(name, ..) if name == "inlined"
+    ));
+}
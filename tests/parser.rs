@@ -0,0 +1,365 @@
+use netl2::logic::{
+    ast::{parse, parse_partial, ASTNode},
+    diagnostic::Diagnostic,
+    error::RuntimeError,
+    interpeter::Interpreter,
+    resolver::resolve,
+    tokenizer::{tokenize, Span, Token},
+    typechecker::check,
+};
+
+fn parse_source(code: &str) -> Result<ASTNode, Vec<Diagnostic>> {
+    parse(tokenize(code).expect("source should tokenize"))
+}
+
+fn statements(code: &str) -> Vec<ASTNode> {
+    match parse_source(code) {
+        Ok(ASTNode::Program(statements)) => statements,
+        other => panic!("{:?} did not parse to a program: {:?}", code, other),
+    }
+}
+
+// = inside expressions
+
+#[test]
+fn equals_in_condition_suggests_equal_equal() {
+    let errors = parse_source("v x = 3\ni x = 3 {\n    p(x)\n}").unwrap_err();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].message,
+        "Unexpected = in an expression, did you mean ==?"
+    );
+    assert_eq!(errors[0].span, Span::new(2, 5));
+}
+
+#[test]
+fn equals_is_rejected_in_every_expression_position() {
+    for code in [
+        "v a = 1\nv b = a = 2",
+        "v a = 1\na = a = 2",
+        "v a = 1\np(a = 1)",
+        "v a = 1\nw a = 1 {}",
+        "f g {\n    r 1 = 1\n}",
+        "v a = 1\np(1 + (a = 2))",
+    ] {
+        let errors = parse_source(code).unwrap_err();
+        assert!(
+            errors[0].message.contains("did you mean =="),
+            "{:?} reported {:?}",
+            code,
+            errors
+        );
+    }
+}
+
+#[test]
+fn equals_still_assigns_in_statements() {
+    let statements = statements("v a = 1\na = 2");
+
+    assert!(matches!(
+        &statements[0],
+        ASTNode::VariableDeclaration(name, None, value, _)
+            if name == "a" && matches!(value.as_ref(), ASTNode::Number(1))
+    ));
+    assert!(matches!(
+        &statements[1],
+        ASTNode::VariableAssignment(name, value, _)
+            if name == "a" && matches!(value.as_ref(), ASTNode::Number(2))
+    ));
+}
+
+#[test]
+fn equal_equal_compares() {
+    let statements = statements("v a = 1\ni a == 1 {}");
+
+    assert!(matches!(
+        &statements[1],
+        ASTNode::If(condition, body, _)
+            if body.is_empty() && matches!(condition.as_ref(), ASTNode::Binary(..))
+    ));
+}
+
+// operator precedence and binding
+
+// binary operators with their binding strength, loosest first
+const BINARY: &[(&str, u8)] = &[
+    ("??", 0),
+    ("||", 1),
+    ("&&", 2),
+    ("==", 3),
+    ("!=", 3),
+    ("<", 3),
+    ("<=", 3),
+    (">", 3),
+    (">=", 3),
+    ("|", 4),
+    ("~", 5),
+    ("&", 6),
+    ("<<", 7),
+    (">>", 7),
+    ("+", 8),
+    ("-", 8),
+    ("*", 9),
+    ("/", 9),
+    ("%", 9),
+    ("^", 11),
+];
+
+const UNARY: &[&str] = &["-", "!", "~"];
+
+fn symbol(token: &Token) -> &'static str {
+    match token {
+        Token::QuestionQuestion => "??",
+        Token::Or => "||",
+        Token::And => "&&",
+        Token::EqualEqual => "==",
+        Token::NotEqual => "!=",
+        Token::LessThan => "<",
+        Token::LessThanEquals => "<=",
+        Token::GreaterThan => ">",
+        Token::GreaterThanEquals => ">=",
+        Token::BitOr => "|",
+        Token::Tilde => "~",
+        Token::BitAnd => "&",
+        Token::ShiftLeft => "<<",
+        Token::ShiftRight => ">>",
+        Token::Plus => "+",
+        Token::Minus => "-",
+        Token::Asterisk => "*",
+        Token::Slash => "/",
+        Token::Percent => "%",
+        Token::Pow => "^",
+        Token::Bang => "!",
+        other => panic!("unexpected operator {:?}", other),
+    }
+}
+
+// writes an expression tree as an s-expression, e.g. (+ (- a) b)
+fn shape(ast: &ASTNode) -> String {
+    match ast {
+        ASTNode::Identifier(name, _) => name.clone(),
+        ASTNode::Number(value) => value.to_string(),
+        ASTNode::Binary(left, operator, right, _) => {
+            format!("({} {} {})", symbol(operator), shape(left), shape(right))
+        }
+        ASTNode::Unary(operator, right, _) => format!("({} {})", symbol(operator), shape(right)),
+        other => panic!("unexpected expression {:?}", other),
+    }
+}
+
+fn expression_shape(expression: &str) -> String {
+    let code = format!("v a = 1\nv b = 2\nv c = 3\nv x = {}", expression);
+    match statements(&code).last() {
+        Some(ASTNode::VariableDeclaration(_, _, value, _)) => shape(value),
+        other => panic!("unexpected statement {:?}", other),
+    }
+}
+
+#[test]
+fn binary_operator_pairs() {
+    for &(first, first_level) in BINARY {
+        for &(second, second_level) in BINARY {
+            let expression = format!("a {} b {} c", first, second);
+            // ^ is the only right associative operator
+            let expected =
+                if first_level > second_level || (first_level == second_level && first != "^") {
+                    format!("({} ({} a b) c)", second, first)
+                } else {
+                    format!("({} a ({} b c))", first, second)
+                };
+
+            assert_eq!(expression_shape(&expression), expected, "{}", expression);
+        }
+    }
+}
+
+#[test]
+fn unary_operators_bind_tighter_than_binary_ones() {
+    for &unary in UNARY {
+        for &(binary, _) in BINARY {
+            let expression = format!("{}a {} b", unary, binary);
+            let expected = if binary == "^" {
+                format!("({} (^ a b))", unary)
+            } else {
+                format!("({} ({} a) b)", binary, unary)
+            };
+            assert_eq!(expression_shape(&expression), expected, "{}", expression);
+
+            let expression = format!("a {} {}b", binary, unary);
+            let expected = format!("({} a ({} b))", binary, unary);
+            assert_eq!(expression_shape(&expression), expected, "{}", expression);
+        }
+    }
+}
+
+#[test]
+fn unary_operators_nest() {
+    for &outer in UNARY {
+        for &inner in UNARY {
+            let expression = format!("{} {}a", outer, inner);
+            let expected = format!("({} ({} a))", outer, inner);
+            assert_eq!(expression_shape(&expression), expected, "{}", expression);
+        }
+    }
+}
+
+#[test]
+fn parentheses_override_binding() {
+    for (expression, expected) in [
+        ("-(a + b)", "(- (+ a b))"),
+        ("(-a) ^ 2", "(^ (- a) 2)"),
+        ("(a ^ b) ^ c", "(^ (^ a b) c)"),
+        ("!(a == b)", "(! (== a b))"),
+        ("(a || b) && c", "(&& (|| a b) c)"),
+        ("a * (b + c)", "(* a (+ b c))"),
+    ] {
+        assert_eq!(expression_shape(expression), expected, "{}", expression);
+    }
+}
+
+// returns
+
+#[test]
+fn return_value_starts_on_the_same_line() {
+    let statements = statements("f g {\n    r 1 + 2\n}\nf k {\n    r\n    p(1)\n}\nr");
+
+    let body = |statement: &ASTNode| match statement {
+        ASTNode::FunctionDeclaration(_, _, body, _) => body.to_vec(),
+        other => panic!("{:?} is not a function", other),
+    };
+    assert!(matches!(
+        &body(&statements[0])[..],
+        [ASTNode::Return(Some(value), _)] if matches!(value.as_ref(), ASTNode::Binary(..))
+    ));
+    assert!(matches!(
+        &body(&statements[1])[..],
+        [ASTNode::Return(None, _), ASTNode::Print(..)]
+    ));
+    assert!(matches!(
+        &statements[2],
+        ASTNode::Return(None, span) if *span == Span::new(8, 1)
+    ));
+}
+
+// compound assignment
+
+#[test]
+fn compound_assignment_keeps_its_operator() {
+    let statements = statements("v a = 1\na += 2 * 3\na ^= 2");
+
+    assert!(matches!(
+        &statements[1],
+        ASTNode::CompoundAssignment(name, Token::Plus, value, _)
+            if name == "a" && matches!(value.as_ref(), ASTNode::Binary(..))
+    ));
+    assert!(matches!(
+        &statements[2],
+        ASTNode::CompoundAssignment(_, Token::Pow, value, _)
+            if matches!(value.as_ref(), ASTNode::Number(2))
+    ));
+}
+
+// keywords
+
+#[test]
+fn keywords_cannot_be_names() {
+    for (code, message, span) in [
+        (
+            "p = 1",
+            "p is a keyword and cannot be used as a name",
+            Span::new(1, 1),
+        ),
+        (
+            "v i = 2",
+            "i is a keyword and cannot be used as a name",
+            Span::new(1, 3),
+        ),
+        (
+            "f w(a) {}",
+            "w is a keyword and cannot be used as a name",
+            Span::new(1, 3),
+        ),
+        (
+            "v x = 1\nr = 2",
+            "r is a keyword and cannot be used as a name",
+            Span::new(2, 1),
+        ),
+    ] {
+        let errors = parse_source(code).unwrap_err();
+        assert_eq!(errors[0].message, message, "{:?}", code);
+        assert_eq!(errors[0].span, span, "{:?}", code);
+    }
+}
+
+#[test]
+fn keywords_before_a_negation_start_their_statement() {
+    let statements =
+        statements("v done = false\ni !done {}\nw !done {}\np !done\nf g {\n    r !done\n}");
+
+    assert!(matches!(&statements[1], ASTNode::If(condition, _, _)
+        if matches!(condition.as_ref(), ASTNode::Unary(Token::Bang, ..))));
+    assert!(matches!(&statements[2], ASTNode::While(..)));
+    assert!(matches!(&statements[3], ASTNode::Print(..)));
+}
+
+#[test]
+fn names_may_contain_digits_and_unicode_letters() {
+    let statements = statements("v var1 = 1\nv größe = var1\nv 名前 = größe");
+
+    assert!(matches!(&statements[0], ASTNode::VariableDeclaration(name, ..) if name == "var1"));
+    assert!(
+        matches!(&statements[2], ASTNode::VariableDeclaration(name, _, value, _)
+        if name == "名前" && matches!(value.as_ref(), ASTNode::Identifier(value, _) if value == "größe"))
+    );
+}
+
+// error recovery
+
+const BROKEN: &str = "v a = \nv b = 2\ni b > {\n    p(b)\n}\np(b +)\nf g( {}\nv c = b * 2\np(c)";
+
+#[test]
+fn every_syntax_error_is_reported() {
+    let errors = parse_source(BROKEN).expect_err("source should not parse");
+    let positions: Vec<_> = errors
+        .iter()
+        .map(|error| (error.span.line, error.span.column))
+        .collect();
+
+    assert_eq!(positions, [(2, 1), (3, 7), (6, 6), (7, 6)]);
+    assert!(errors[0].message.ends_with("but got V"));
+    assert!(errors[3].message.starts_with("Expected identifier"));
+}
+
+#[test]
+fn parsing_restarts_after_a_bad_statement() {
+    let (ast, _) = parse_partial(tokenize(BROKEN).unwrap());
+    let ASTNode::Program(statements) = ast else {
+        panic!("{:?} is not a program", ast);
+    };
+
+    assert!(matches!(statements[0], ASTNode::Error(span) if span == Span::new(1, 1)));
+    assert!(matches!(&statements[1], ASTNode::VariableDeclaration(name, ..) if name == "b"));
+    assert!(matches!(statements[2], ASTNode::Error(span) if span == Span::new(3, 1)));
+    assert!(matches!(statements[3], ASTNode::Error(span) if span == Span::new(6, 1)));
+    assert!(matches!(statements[4], ASTNode::Error(span) if span == Span::new(7, 1)));
+    assert!(matches!(&statements[5], ASTNode::VariableDeclaration(name, ..) if name == "c"));
+    assert!(matches!(statements[6], ASTNode::Print(..)));
+}
+
+#[test]
+fn error_nodes_reach_the_later_passes_safely() {
+    let (ast, _) = parse_partial(tokenize(BROKEN).unwrap());
+
+    assert!(resolve(&ast).is_empty());
+    assert!(check(&ast).is_empty());
+
+    let mut interpreter = Interpreter::new();
+    let error = interpreter
+        .run(&ast)
+        .expect_err("the first statement should fail");
+    assert!(matches!(
+        error,
+        RuntimeError::InvalidValue(_, span) if span == Span::new(1, 1)
+    ));
+}
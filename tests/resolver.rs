@@ -0,0 +1,127 @@
+use std::time::{Duration, Instant};
+
+use netl2::logic::{ast::parse, resolver::resolve, tokenizer::tokenize};
+
+// diagnostics as printed by the CLI, e.g. "Error: Variable x is not declared at 1:3"
+fn diagnostics(code: &str) -> Vec<String> {
+    let ast = parse(tokenize(code).expect("script should tokenize")).expect("script should parse");
+    resolve(&ast)
+        .into_iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect()
+}
+
+// undeclared names
+
+#[test]
+fn reports_undeclared_names() {
+    assert_eq!(
+        diagnostics("p x\nfoo()\ny = 2"),
+        [
+            "Error: Variable x is not declared at 1:3",
+            "Error: Function foo is not declared at 2:4",
+            "Error: Cannot assign to undeclared variable y at 3:1",
+        ]
+    );
+}
+
+#[test]
+fn reports_names_used_before_their_declaration() {
+    assert_eq!(
+        diagnostics("p x\nv x = 1"),
+        ["Error: Variable x is used before it is declared at 1:3"]
+    );
+}
+
+#[test]
+fn locals_are_not_visible_outside_their_function() {
+    assert_eq!(
+        diagnostics("f g {\n    v local = 1\n}\ng()\np local"),
+        ["Error: Variable local is used before it is declared at 5:3"]
+    );
+}
+
+// function bodies
+
+#[test]
+fn checks_function_bodies_where_they_are_called() {
+    assert!(diagnostics("f show {\n    p(name)\n}\nv name = \"a\"\nshow()").is_empty());
+    assert_eq!(
+        diagnostics("f show {\n    p(name)\n}\nshow()\nv name = \"a\""),
+        ["Error: Variable name is used before it is declared at 2:7"]
+    );
+}
+
+#[test]
+fn reports_wrong_argument_counts() {
+    assert_eq!(
+        diagnostics("f add(a, b) {\n    r a + b\n}\nadd(1)\nlen(1, 2)"),
+        [
+            "Error: Function add expects 2 arguments but got 1 at 4:4",
+            "Error: Function len expects 1 arguments but got 2 at 5:4",
+        ]
+    );
+}
+
+#[test]
+fn warns_about_variables_shadowing_functions() {
+    assert_eq!(
+        diagnostics("f id(a) {\n    r a\n}\nv id = 1"),
+        ["Warning: Variable id shadows the function of the same name at 4:3"]
+    );
+}
+
+// loops
+
+#[test]
+fn declarations_from_earlier_iterations_are_warnings() {
+    assert_eq!(
+        diagnostics(
+            "v n = 0\nw n < 2 {\n    i n == 1 {\n        p seen\n    }\n    v seen = 5\n    n += 1\n}"
+        ),
+        ["Warning: Variable seen is used before it is declared on the first iteration of the loop at 4:11"]
+    );
+}
+
+#[test]
+fn names_missing_on_every_iteration_are_errors() {
+    assert_eq!(
+        diagnostics("w false {\n    p other\n    i true {\n        p later\n    }\n}\nv later = 1"),
+        [
+            "Error: Variable other is not declared at 2:7",
+            "Error: Variable later is used before it is declared at 4:11",
+        ]
+    );
+}
+
+#[test]
+fn deeply_nested_loops_are_resolved_once() {
+    let depth = 40;
+    let mut code = String::from("v n = 0\n");
+    for level in 0..depth {
+        code += &format!("{}w n < 1 {{\n", "    ".repeat(level));
+    }
+    code += &format!(
+        "{}p(inner)\n{}v inner = n\n",
+        "    ".repeat(depth),
+        "    ".repeat(depth)
+    );
+    for level in (0..depth).rev() {
+        code += &format!("{}}}\n", "    ".repeat(level));
+    }
+
+    let started = Instant::now();
+    let diagnostics = diagnostics(&code);
+
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].starts_with("Warning: Variable inner is used before it is declared"));
+}
+
+#[test]
+fn functions_called_in_a_loop_see_its_later_declarations() {
+    assert_eq!(
+        diagnostics("f show {\n    p(seen)\n}\nw false {\n    show()\n    v seen = 1\n}"),
+        ["Warning: Variable seen is used before it is declared on the first iteration of the loop at 2:7"]
+    );
+}
@@ -0,0 +1,189 @@
+mod common;
+
+use common::{parse_source, run};
+use netl2::logic::{
+    capabilities::{Capabilities, Capability},
+    error::RuntimeError,
+    interpeter::Interpreter,
+    limits::Limits,
+    stdlib::NATIVES,
+    tester::{run_tests, test_names},
+    tokenizer::Span,
+};
+
+// assertions
+
+#[test]
+fn passing_assertions_do_not_stop_the_script() {
+    run("assert(1 < 2)\nassert_eq([1, \"a\"], [1, \"a\"])\nassert_eq(\"x\" + \"y\", \"xy\")")
+        .unwrap();
+}
+
+#[test]
+fn failed_assert_reports_the_expression() {
+    let error = run("v x = 3\nassert(x * 2 > 10)")
+        .err()
+        .expect("script should fail");
+
+    assert_eq!(
+        error,
+        RuntimeError::AssertionFailed(Some("x * 2 > 10".to_string()), None, Span::new(2, 7))
+    );
+}
+
+#[test]
+fn failed_assert_eq_reports_both_values() {
+    let error = run("f add(a, b) {\n    r a + b\n}\nassert_eq(add(1, 2), \"3\")")
+        .err()
+        .expect("script should fail");
+
+    assert_eq!(
+        error,
+        RuntimeError::AssertionFailed(
+            Some("add(1, 2) == \"3\"".to_string()),
+            Some(("3".to_string(), "\"3\"".to_string())),
+            Span::new(4, 10)
+        )
+    );
+    assert_eq!(
+        error.to_string(),
+        "Error: Assertion failed: add(1, 2) == \"3\" at 4:10\n  left: 3\n  right: \"3\""
+    );
+}
+
+#[test]
+fn failed_assertion_exits_with_a_non_zero_code() {
+    let mut interpreter = Interpreter::new();
+    let result = interpreter.run(&parse_source("assert(false)"));
+
+    assert!(matches!(result, Err(RuntimeError::AssertionFailed(..))));
+    assert_eq!(interpreter.exit_code(), 1);
+}
+
+#[test]
+fn only_assertions_report_their_source() {
+    let assertions: Vec<_> = NATIVES
+        .iter()
+        .filter(|native| native.assertion)
+        .map(|native| native.name)
+        .collect();
+    assert_eq!(assertions, ["assert", "assert_eq"]);
+
+    // a function of the script with an assert-like name fails like any other
+    let error = run("f assert_positive(n) {\n    assert(n > 0)\n}\nassert_positive(-1)")
+        .err()
+        .expect("script should fail");
+    assert_eq!(
+        error,
+        RuntimeError::AssertionFailed(Some("n > 0".to_string()), None, Span::new(2, 11))
+    );
+}
+
+// test discovery
+
+#[test]
+fn finds_top_level_test_functions_without_parameters() {
+    let ast = parse_source(
+        "f test_one {}\nf test_two() {}\nf helper {}\nf test_with(x) {}\nf outer {\n    f test_inner {}\n}",
+    );
+
+    assert_eq!(test_names(&ast), ["test_one", "test_two"]);
+}
+
+#[test]
+fn tests_do_not_see_each_others_changes() {
+    let ast = parse_source(
+        "v count = 0\nf test_first {\n    count += 1\n    assert_eq(count, 1)\n}\nf test_second {\n    count += 1\n    assert_eq(count, 1)\n}",
+    );
+
+    let results = run_tests(&ast, Limits::default(), &Capabilities::default());
+
+    assert_eq!(results.len(), 2);
+    for result in results {
+        assert!(result.result.is_ok(), "{} failed", result.name);
+    }
+}
+
+#[test]
+fn top_level_runs_once_for_all_tests() {
+    // clock and random would differ between runs of the top level
+    let ast = parse_source(
+        "v seed = random(1000000)\nv first = 0\nf test_first {\n    first = seed\n    assert(first == seed)\n}\nf test_second {\n    assert_eq(first, 0)\n    assert(seed >= 0)\n}",
+    );
+
+    let results = run_tests(&ast, Limits::default(), &Capabilities::default());
+
+    assert_eq!(results.len(), 2);
+    for result in results {
+        assert!(result.result.is_ok(), "{} failed", result.name);
+    }
+}
+
+#[test]
+fn failing_top_level_fails_every_test() {
+    let ast = parse_source("f test_one {}\nf test_two {}\nv broken = 1 / 0");
+
+    let results = run_tests(&ast, Limits::default(), &Capabilities::default());
+
+    assert_eq!(results.len(), 2);
+    for result in results {
+        assert!(matches!(
+            result.result,
+            Err(RuntimeError::DivisionByZero(span)) if span == Span::new(3, 14)
+        ));
+    }
+
+    let ast = parse_source("set_exit_code(2)\nf test_one {}");
+    let results = run_tests(&ast, Limits::default(), &Capabilities::default());
+    assert!(matches!(results[0].result, Err(RuntimeError::Exit(2, _))));
+}
+
+#[test]
+fn reports_failing_tests() {
+    let ast = parse_source(
+        "f test_passes {\n    assert(true)\n}\nf test_fails {\n    assert_eq(1, 2)\n}\nf test_exits {\n    exit(3)\n}\nf test_exits_cleanly {\n    exit(0)\n}",
+    );
+
+    let results: Vec<(String, bool)> = run_tests(&ast, Limits::default(), &Capabilities::default())
+        .into_iter()
+        .map(|test| (test.name, test.result.is_ok()))
+        .collect();
+
+    assert_eq!(
+        results,
+        [
+            ("test_passes".to_string(), true),
+            ("test_fails".to_string(), false),
+            ("test_exits".to_string(), false),
+            ("test_exits_cleanly".to_string(), true),
+        ]
+    );
+}
+
+#[test]
+fn tests_run_with_the_given_limits_and_capabilities() {
+    let ast = parse_source(
+        "f test_loops {\n    w true {\n        v a = 1\n    }\n}\nf test_reads_env {\n    env(\"PATH\")\n}",
+    );
+    let limits = Limits {
+        max_steps: Some(100),
+        ..Limits::default()
+    };
+
+    let results = run_tests(&ast, limits, &Capabilities::default());
+    assert!(matches!(
+        results[0].result,
+        Err(RuntimeError::StepLimit(100, _))
+    ));
+    assert!(matches!(
+        results[1].result,
+        Err(RuntimeError::PermissionDenied(..))
+    ));
+
+    let results = run_tests(
+        &ast,
+        limits,
+        &Capabilities::default().allow(Capability::Env),
+    );
+    assert!(results[1].result.is_ok());
+}
@@ -0,0 +1,160 @@
+use netl2::logic::tokenizer::{suggestion, tokenize, LexError, Span, Token};
+
+fn tokens(code: &str) -> Vec<Token> {
+    tokenize(code)
+        .expect("source should tokenize")
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect()
+}
+
+fn lex_error(code: &str) -> LexError {
+    tokenize(code).expect_err("source should not tokenize")
+}
+
+// identifiers
+
+#[test]
+fn identifiers_continue_with_digits_and_underscores() {
+    assert_eq!(
+        tokens("var1 _x2 a_b_3"),
+        [
+            Token::Identifier("var1".to_string()),
+            Token::Identifier("_x2".to_string()),
+            Token::Identifier("a_b_3".to_string()),
+            Token::EndOfFile
+        ]
+    );
+    // a digit cannot start a name
+    assert_eq!(
+        tokens("1a"),
+        [
+            Token::Number(1),
+            Token::Identifier("a".to_string()),
+            Token::EndOfFile
+        ]
+    );
+}
+
+#[test]
+fn identifiers_follow_unicode_rules() {
+    assert_eq!(
+        tokens("größe 名前 café2"),
+        [
+            Token::Identifier("größe".to_string()),
+            Token::Identifier("名前".to_string()),
+            Token::Identifier("café2".to_string()),
+            Token::EndOfFile
+        ]
+    );
+    assert_eq!(lex_error("v a€ = 1").character, '€');
+}
+
+// number literals
+
+#[test]
+fn decimal_literals_cover_the_i32_range() {
+    assert_eq!(
+        tokens("2147483647 0"),
+        [Token::Number(i32::MAX), Token::Number(0), Token::EndOfFile]
+    );
+}
+
+#[test]
+fn decimal_literals_out_of_range_are_errors() {
+    let error = lex_error("v x = 99999999999");
+    assert_eq!((error.character, error.span), ('9', Span::new(1, 16)));
+    assert!(error.suggestion.is_some());
+
+    // the minus is an operator of its own, so the literal alone is too large
+    let error = lex_error("p -2147483648");
+    assert_eq!((error.character, error.span), ('8', Span::new(1, 13)));
+}
+
+#[test]
+fn hexadecimal_and_binary_literals_cover_all_32_bits() {
+    assert_eq!(
+        tokens("0xff 0x7FFFFFFF 0xFFFFFFFF 0x80000000 0b101 0b0"),
+        [
+            Token::Number(255),
+            Token::Number(i32::MAX),
+            Token::Number(-1),
+            Token::Number(i32::MIN),
+            Token::Number(5),
+            Token::Number(0),
+            Token::EndOfFile
+        ]
+    );
+}
+
+#[test]
+fn hexadecimal_and_binary_literals_need_digits_in_range() {
+    for (code, prefix_column) in [
+        ("p 0x", 4),
+        ("p 0xg", 4),
+        ("p 0x1FFFFFFFF", 4),
+        ("v a = 0b", 8),
+    ] {
+        let error = lex_error(code);
+        assert_eq!(error.span, Span::new(1, prefix_column), "{:?}", code);
+        assert!(error.suggestion.is_some());
+    }
+    let error = lex_error(&format!("p 0b1{}", "0".repeat(32)));
+    assert_eq!((error.character, error.span), ('b', Span::new(1, 4)));
+}
+
+// lex errors
+
+#[test]
+fn lex_errors_point_at_the_character() {
+    let error = lex_error("v a = 1\nv b = a $ 2");
+
+    assert_eq!((error.character, error.span), ('$', Span::new(2, 9)));
+    assert_eq!(error.message(), "Unexpected character '$'");
+    assert_eq!(error.suggestion, None);
+    assert_eq!(error.to_string(), "Error: Unexpected character '$' at 2:9");
+}
+
+#[test]
+fn common_mistakes_get_a_suggestion() {
+    for (code, span, suggestion) in [
+        (
+            "v a = 1;",
+            Span::new(1, 8),
+            "statements end at the end of the line, remove the ;",
+        ),
+        ("# comment", Span::new(1, 1), "comments start with //"),
+        ("p('hi')", Span::new(1, 3), "strings use double quotes"),
+        (
+            "v a = 1.5",
+            Span::new(1, 8),
+            "numbers have no fractional part",
+        ),
+    ] {
+        let error = lex_error(code);
+        assert_eq!(error.span, span, "{:?}", code);
+        assert_eq!(error.suggestion, Some(suggestion), "{:?}", code);
+        assert!(error
+            .to_string()
+            .ends_with(&format!("\n  help: {}", suggestion)));
+    }
+}
+
+#[test]
+fn single_ampersand_and_bar_are_bitwise_operators() {
+    assert_eq!(
+        tokens("a & b | c && d || g")
+            .into_iter()
+            .filter(|token| !matches!(token, Token::Identifier(_)))
+            .collect::<Vec<_>>(),
+        [
+            Token::BitAnd,
+            Token::BitOr,
+            Token::And,
+            Token::Or,
+            Token::EndOfFile
+        ]
+    );
+    assert_eq!(suggestion('&'), Some("use && for a logical and"));
+    assert_eq!(suggestion('|'), Some("use || for a logical or"));
+}
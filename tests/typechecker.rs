@@ -0,0 +1,116 @@
+mod common;
+
+use common::{parse_source, type_errors};
+use netl2::logic::{tokenizer::Span, typechecker::check};
+
+// scopes
+
+#[test]
+fn declarations_in_functions_shadow_globals() {
+    assert!(type_errors(
+        "v name = \"text\"\nf count {\n    v name = 5\n    name += 1\n    r name\n}\nname = \"other\""
+    )
+    .is_empty());
+}
+
+#[test]
+fn declarations_in_a_function_keep_their_type() {
+    assert_eq!(
+        type_errors("f count {\n    v n = 5\n    i true {\n        v n = \"five\"\n    }\n}"),
+        ["Variable n has type int but is assigned str"]
+    );
+}
+
+#[test]
+fn assignments_in_functions_check_the_global() {
+    assert_eq!(
+        type_errors("v total = 0\nf reset {\n    total = \"none\"\n}"),
+        ["Variable total has type int but is assigned str"]
+    );
+}
+
+// operators
+
+#[test]
+fn operators_reject_operands_of_the_wrong_type() {
+    assert_eq!(
+        type_errors("v a = \"a\" - 1\nv b = -\"x\"\nv c = !3\nv d = 1 < \"x\""),
+        [
+            "Operator - cannot be applied to str and int",
+            "Operator - cannot be applied to str",
+            "Operator ! cannot be applied to int",
+            "Operator < cannot be applied to int and str"
+        ]
+    );
+}
+
+#[test]
+fn operator_errors_point_at_the_operator() {
+    let spans: Vec<_> = check(&parse_source("v a = 1\nv b = \"a\" - a"))
+        .into_iter()
+        .map(|diagnostic| diagnostic.span)
+        .collect();
+    assert_eq!(spans, [Span::new(2, 11)]);
+}
+
+#[test]
+fn operators_accept_the_types_they_work_on() {
+    assert!(type_errors(
+        "v a = \"a\" + \"b\"\nv b = 2 ^ 3 % 2\nv c = !(a == \"ab\") && 1 < b\nv d = nil ?? 1"
+    )
+    .is_empty());
+}
+
+#[test]
+fn bitwise_operators_on_bools_suggest_the_logical_ones() {
+    assert_eq!(
+        type_errors("v a = true & false\nv b = true | false"),
+        [
+            "Operator & cannot be applied to bool and bool (use && for a logical and)",
+            "Operator | cannot be applied to bool and bool (use || for a logical or)"
+        ]
+    );
+}
+
+// conditions
+
+#[test]
+fn conditions_must_be_bool() {
+    assert_eq!(
+        type_errors("i 1 {\n}\nw \"x\" {\n}\nv n = 3\ni n > 1 {\n}\nw n == 3 {\n}"),
+        [
+            "Condition of i must be bool but is int",
+            "Condition of w must be bool but is str"
+        ]
+    );
+}
+
+#[test]
+fn conditions_of_unknown_type_are_allowed() {
+    assert!(type_errors(
+        "f check(value) {\n    i value {\n        r 1\n    }\n    w value {\n    }\n}"
+    )
+    .is_empty());
+}
+
+// annotations
+
+#[test]
+fn annotations_must_match_the_initial_value() {
+    assert_eq!(
+        type_errors("v n: int = \"five\"\nv s: str = 5\nv b: bool = 1\nv ok: int = 2"),
+        [
+            "Variable n is annotated as int but initialized with str",
+            "Variable s is annotated as str but initialized with int",
+            "Variable b is annotated as bool but initialized with int"
+        ]
+    );
+}
+
+#[test]
+fn annotations_fix_the_type_of_later_assignments() {
+    assert_eq!(
+        type_errors("v flag: bool = true\nflag = false\nflag = 3"),
+        ["Variable flag has type bool but is assigned int"]
+    );
+}